@@ -0,0 +1,164 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+const GLYPHS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ.,!?:;-+/'\"()%=_<>#*";
+const GLYPH_COLUMNS: usize = 10;
+const GLYPH_SIZE: Vec2 = vec2(3., 5.);
+const FONT_PATH: &str = "spritesheets/font.png";
+
+#[derive(Resource)]
+pub struct BitmapFont {
+    pub layout: Handle<TextureAtlasLayout>,
+    pub texture: Handle<Image>,
+}
+impl FromWorld for BitmapFont {
+    fn from_world(world: &mut World) -> Self {
+        let rows = GLYPHS.chars().count().div_ceil(GLYPH_COLUMNS);
+        let texture_atlas = TextureAtlasLayout::from_grid(
+            GLYPH_SIZE,
+            GLYPH_COLUMNS,
+            rows,
+            Some(vec2(1., 1.)),
+            Some(vec2(1., 1.)),
+        );
+        let layout = world
+            .get_resource_mut::<Assets<TextureAtlasLayout>>()
+            .unwrap()
+            .add(texture_atlas);
+        let texture = world.resource::<AssetServer>().load(FONT_PATH);
+        Self { layout, texture }
+    }
+}
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BitmapTextAnchor {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+/// Text drawn with the pixel font. `scale` is the size of one font pixel in world units.
+#[derive(Component, Clone, Debug)]
+pub struct BitmapText {
+    pub text: String,
+    pub color: Color,
+    pub scale: f32,
+    pub anchor: BitmapTextAnchor,
+}
+impl Default for BitmapText {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            color: Color::WHITE,
+            scale: 4.,
+            anchor: BitmapTextAnchor::Center,
+        }
+    }
+}
+impl BitmapText {
+    pub fn new(text: impl Into<String>, scale: f32) -> Self {
+        Self {
+            text: text.into(),
+            scale,
+            ..Default::default()
+        }
+    }
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn with_anchor(mut self, anchor: BitmapTextAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+}
+#[derive(Bundle, Default)]
+pub struct BitmapTextBundle {
+    pub text: BitmapText,
+    pub spatial: SpatialBundle,
+}
+impl BitmapTextBundle {
+    pub fn new(text: BitmapText, translation: Vec3) -> Self {
+        Self {
+            text,
+            spatial: SpatialBundle::from_transform(Transform::from_translation(translation)),
+        }
+    }
+}
+#[derive(Component)]
+struct Glyph;
+pub struct BitmapFontPlugin;
+impl Plugin for BitmapFontPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BitmapFont>()
+            .add_systems(PostUpdate, layout_bitmap_text);
+    }
+}
+pub fn glyph_index(character: char) -> Option<usize> {
+    let character = character.to_ascii_uppercase();
+    GLYPHS.chars().position(|glyph| glyph == character)
+}
+/// Width of the widest line in font pixels.
+pub fn text_width(text: &str) -> f32 {
+    text.lines()
+        .map(|line| line.chars().count())
+        .max()
+        .map(|length| (length as f32 * (GLYPH_SIZE.x + 1.) - 1.).max(0.))
+        .unwrap_or(0.)
+}
+fn layout_bitmap_text(
+    mut commands: Commands,
+    font: Res<BitmapFont>,
+    texts: Query<(Entity, &BitmapText, Option<&Children>), Changed<BitmapText>>,
+    glyphs: Query<(), With<Glyph>>,
+) {
+    for (entity, text, children) in texts.iter() {
+        if let Some(children) = children {
+            for &child in children.iter() {
+                if glyphs.contains(child) {
+                    commands.entity(child).despawn_recursive();
+                }
+            }
+        }
+        let line_count = text.text.lines().count().max(1) as f32;
+        let line_height = GLYPH_SIZE.y + 1.;
+        let top = (line_count * line_height - 1.) / 2.;
+        commands.entity(entity).with_children(|parent| {
+            for (row, line) in text.text.lines().enumerate() {
+                let width = text_width(line);
+                let left = match text.anchor {
+                    BitmapTextAnchor::Left => 0.,
+                    BitmapTextAnchor::Center => -width / 2.,
+                    BitmapTextAnchor::Right => -width,
+                };
+                for (column, character) in line.chars().enumerate() {
+                    let index = match glyph_index(character) {
+                        Some(index) => index,
+                        None if character.is_whitespace() => continue,
+                        None => glyph_index('?').unwrap(),
+                    };
+                    let x = left + column as f32 * (GLYPH_SIZE.x + 1.) + GLYPH_SIZE.x / 2.;
+                    let y = top - row as f32 * line_height - GLYPH_SIZE.y / 2.;
+                    parent.spawn((
+                        SpriteSheetBundle {
+                            atlas: TextureAtlas {
+                                layout: font.layout.clone(),
+                                index,
+                            },
+                            texture: font.texture.clone(),
+                            sprite: Sprite {
+                                color: text.color,
+                                custom_size: Some(GLYPH_SIZE * text.scale),
+                                ..Default::default()
+                            },
+                            transform: Transform::from_translation(
+                                vec2(x, y).extend(0.) * text.scale,
+                            ),
+                            ..Default::default()
+                        },
+                        Glyph,
+                    ));
+                }
+            }
+        });
+    }
+}
@@ -41,9 +41,7 @@ pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CameraSettings::default())
-            .insert_resource(CursorWorldPosition {
-                0: Vec2::new(0., 0.),
-            })
+            .insert_resource(CursorWorldPosition(Vec2::new(0., 0.)))
             .add_systems(Startup, camera_setup)
             .add_systems(Update, camera_movement)
             .add_systems(Update, definitely_my_cursor_system_which_isnt_stolen);
@@ -79,16 +77,12 @@ fn camera_movement(
 ) {
     let mut scroll_direction = 0;
     for event in scroll_event_reader.read() {
-        scroll_direction = match event.unit {
-            _ => {
-                if event.y >= 1. {
-                    1
-                } else if event.y == 0. {
-                    0
-                } else {
-                    -1
-                }
-            }
+        scroll_direction = if event.y >= 1. {
+            1
+        } else if event.y == 0. {
+            0
+        } else {
+            -1
         }
     }
     //Move Camera
@@ -102,24 +96,17 @@ fn camera_movement(
         {
             camera_settings.is_going_to_target_position = false;
         } else {
-            if camera.1.scale > camera_settings.desired_scale {
-                camera.1.scale -= (camera.1.scale - camera_settings.desired_scale)
-                    / camera_settings.zoom_smoothing
-                    / 2.
-                    * time.delta_seconds();
-            } else {
-                camera.1.scale -= (camera.1.scale - camera_settings.desired_scale)
-                    / camera_settings.zoom_smoothing
-                    / 2.
-                    * time.delta_seconds();
-            }
+            camera.1.scale -= (camera.1.scale - camera_settings.desired_scale)
+                / camera_settings.zoom_smoothing
+                / 2.
+                * time.delta_seconds();
             camera.0.translation.x -= (camera.0.translation.x - camera_settings.target_position.x)
                 * 10.
                 * time.delta_seconds();
             camera.0.translation.y -= (camera.0.translation.y - camera_settings.target_position.y)
                 * 10.
                 * time.delta_seconds();
-            return ();
+            return;
         }
     }
     let world_screen_displacement: Vec2 = Vec2 {
@@ -172,7 +159,7 @@ fn camera_movement(
             (camera_settings.middle_mouse_start_position.y - cursor_world_position.0.y) / 2.;
     }
     if mouse_input.pressed(MouseButton::Middle) {
-        return ();
+        return;
     }
     let mut x_axis: f32 = 0.;
     if keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft) {
@@ -223,7 +210,7 @@ fn _calculate_world_coordinates(
     if let Some(mut screen_pos) = window.cursor_position() {
         screen_pos.y *= -1.;
         // get the size of the window
-        let window_size = Vec2::new(window.width() as f32, window.height() as f32);
+        let window_size = Vec2::new(window.width(), window.height());
 
         // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
         let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
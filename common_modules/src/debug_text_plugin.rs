@@ -29,7 +29,7 @@ impl Plugin for DebugTextPlugin {
     fn build(&self, app: &mut App) {
         let mut fps_timer = Timer::from_seconds(0.05, TimerMode::Repeating);
         fps_timer.tick(Duration::from_secs_f32(2.));
-        app.add_plugins(FrameTimeDiagnosticsPlugin::default())
+        app.add_plugins(FrameTimeDiagnosticsPlugin)
             .insert_resource(FPSTimer(fps_timer))
             .add_systems(Startup, create_debug_overlay)
             .add_systems(Update, (update_debug_overlay, fps_update));
@@ -1,2 +1,3 @@
+pub mod bitmap_font_plugin;
 pub mod camera_plugin;
 pub mod debug_text_plugin;
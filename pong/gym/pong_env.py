@@ -0,0 +1,41 @@
+"""Minimal client for `pong --gym`, the headless training environment.
+
+    env = PongEnv(["cargo", "run", "--release", "--", "--gym"])
+    observation = env.reset(seed=1)
+    observation, reward, done = env.step(1.0, -0.5)
+
+Rewards are from the left paddle's side, the right paddle's reward is the negation.
+"""
+import json
+import subprocess
+
+
+class PongEnv:
+    def __init__(self, command=("./target/release/pong", "--gym")):
+        self.process = subprocess.Popen(
+            list(command), stdin=subprocess.PIPE, stdout=subprocess.PIPE, text=True
+        )
+        self.shape = None
+
+    def _call(self, request):
+        self.process.stdin.write(json.dumps(request) + "\n")
+        self.process.stdin.flush()
+        response = json.loads(self.process.stdout.readline())
+        if "error" in response:
+            raise RuntimeError(response["error"])
+        return response
+
+    def reset(self, seed=None):
+        response = self._call({"cmd": "reset", "seed": seed})
+        self.shape = response["shape"]
+        return response["observation"]
+
+    def step(self, action_left, action_right, repeat=1):
+        response = self._call(
+            {"cmd": "step", "left": action_left, "right": action_right, "repeat": repeat}
+        )
+        return response["observation"], response["reward"], response["done"]
+
+    def close(self):
+        self.process.stdin.close()
+        self.process.wait()
@@ -0,0 +1,140 @@
+use bevy::math::vec3;
+use bevy::prelude::*;
+use common_modules::bitmap_font_plugin::{BitmapText, BitmapTextBundle};
+use serde::{Deserialize, Serialize};
+
+use crate::game_events::{GameEventSet, MatchOver, PaddleHit};
+use crate::layout_plugin::Layout;
+use crate::match_stats::{record_play, MatchStats};
+use crate::menu_plugin::AppState;
+use crate::profiles::Profiles;
+use crate::theme_plugin::{ThemeRole, Themed};
+use crate::Player;
+
+const LONG_RALLY: u32 = 20;
+const FAST_RETURN_SPEED: f32 = 1500.;
+/// Points the winner of a comeback must have trailed by, without scoring any.
+const COMEBACK_DEFICIT: u32 = 5;
+const TOAST_SECONDS: f32 = 3.;
+const TOAST_SPACING: f32 = 16.;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    /// A point with twenty paddle hits, awarded to both players.
+    LongRally,
+    /// A win without conceding a point.
+    Shutout,
+    FastReturn,
+    /// A win after trailing 0-5.
+    Comeback,
+}
+impl Achievement {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::LongRally => "Long Rally",
+            Achievement::Shutout => "Shutout",
+            Achievement::FastReturn => "Speed Demon",
+            Achievement::Comeback => "Comeback",
+        }
+    }
+}
+#[derive(Component)]
+struct Toast(Timer);
+/// Unlocks achievements for the profiles playing from the game events, showing a toast for each
+/// new one. Guests don't earn achievements.
+pub struct AchievementsPlugin;
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                //Practice doesn't count towards achievements
+                track_achievements
+                    .after(GameEventSet)
+                    .after(record_play)
+                    .run_if(in_state(AppState::Playing)),
+                update_toasts,
+            ),
+        );
+    }
+}
+fn other(player: Player) -> Player {
+    match player {
+        Player::Left => Player::Right,
+        Player::Right => Player::Left,
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn track_achievements(
+    mut commands: Commands,
+    mut stats: ResMut<MatchStats>,
+    mut profiles: ResMut<Profiles>,
+    mut hits: EventReader<PaddleHit>,
+    mut match_overs: EventReader<MatchOver>,
+    toasts: Query<(), With<Toast>>,
+    layout: Res<Layout>,
+) {
+    //The stats already count this frame's hits, goals and any head start, and are reset with the
+    //match so nothing carries over from an abandoned one
+    let mut earned = vec![];
+    let mut new_hits = 0;
+    for hit in hits.read() {
+        new_hits += 1;
+        if hit.speed >= FAST_RETURN_SPEED {
+            earned.push((hit.player, Achievement::FastReturn));
+        }
+    }
+    if new_hits > 0 && stats.rally >= LONG_RALLY && stats.rally - new_hits < LONG_RALLY {
+        earned.push((Player::Left, Achievement::LongRally));
+        earned.push((Player::Right, Achievement::LongRally));
+    }
+    //Checked every frame so a head start that puts a side 0-5 down counts too
+    for player in [Player::Left, Player::Right] {
+        let (own, opponent) = (*stats.score.get(player), *stats.score.get(other(player)));
+        if own == 0 && opponent >= COMEBACK_DEFICIT && !stats.trailed.get(player) {
+            *stats.trailed.get_mut(player) = true;
+        }
+    }
+    for end in match_overs.read() {
+        if *stats.score.get(other(end.winner)) == 0 {
+            earned.push((end.winner, Achievement::Shutout));
+        }
+        if *stats.trailed.get(end.winner) {
+            earned.push((end.winner, Achievement::Comeback));
+        }
+    }
+
+    let mut unlocked = vec![];
+    for (player, achievement) in earned {
+        let Some(profile) = profiles.player_mut(player) else {
+            continue;
+        };
+        if !profile.achievements.contains(&achievement) {
+            profile.achievements.push(achievement);
+            unlocked.push(format!("{} UNLOCKED {}", profile.name, achievement.name()));
+        }
+    }
+    if unlocked.is_empty() {
+        return;
+    }
+    profiles.save();
+    let top = layout.view_size().y / 2. - 12.;
+    for (i, text) in unlocked.into_iter().enumerate() {
+        let slot = toasts.iter().count() + i;
+        commands.spawn((
+            BitmapTextBundle::new(
+                BitmapText::new(text, 2.),
+                vec3(0., top - slot as f32 * TOAST_SPACING, 8.),
+            ),
+            Themed(ThemeRole::Accent),
+            Toast(Timer::from_seconds(TOAST_SECONDS, TimerMode::Once)),
+        ));
+    }
+}
+fn update_toasts(mut commands: Commands, mut toasts: Query<(Entity, &mut Toast)>, time: Res<Time>) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
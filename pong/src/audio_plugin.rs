@@ -0,0 +1,376 @@
+use bevy::audio::{AddAudioSource, PlaybackMode, SpatialScale, Volume};
+use bevy::ecs::system::SystemParam;
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use common_modules::debug_text_plugin::{change_debug_text, DebugText};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{load_config, save_config};
+use crate::game_events::{GameEventSet, GoalScored, PaddleHit, WallBounce};
+use crate::layout_plugin::{CourtRoot, Layout};
+use crate::menu_plugin::{add_menu_entry, on_off, MenuAction, MenuEntry};
+use crate::sound_pack::{available_sound_packs, load_sound_pack, SoundPackEntry};
+use crate::synth::SynthSound;
+use crate::{Settings, PLAY_AREA};
+
+const AUDIO_CONFIG: &str = "audio";
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub muted: bool,
+    pub max_voices: usize,
+    pub sound_pack: String,
+    /// Pans sound effects left and right by where they happen on the court.
+    pub spatial: bool,
+}
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            sfx_volume: 1.,
+            music_volume: 0.6,
+            muted: false,
+            max_voices: 8,
+            sound_pack: "classic".into(),
+            spatial: true,
+        }
+    }
+}
+impl AudioSettings {
+    /// Final volume of a bus after the master volume and mute are applied.
+    pub fn bus_volume(&self, bus: f32) -> f32 {
+        if self.muted {
+            return 0.;
+        }
+        (self.master_volume * bus).clamp(0., 1.)
+    }
+}
+#[derive(Clone)]
+enum SoundVariant {
+    Sample(Handle<AudioSource>),
+    Synth(Handle<SynthSound>),
+}
+struct SoundGroup {
+    variants: Vec<SoundVariant>,
+    pitch_variation: f32,
+}
+#[derive(Resource, Default)]
+pub struct AudioManager {
+    groups: HashMap<String, SoundGroup>,
+    loaded_pack: Option<String>,
+    pub sounds_played: u32,
+    pending_voices: usize,
+    output_enabled: bool,
+}
+#[derive(Component)]
+struct SfxVoice;
+/// Plays sound effects through the SFX bus, respecting mute and the voice cap.
+#[derive(SystemParam)]
+pub struct Sfx<'w, 's> {
+    commands: Commands<'w, 's>,
+    manager: ResMut<'w, AudioManager>,
+    settings: Res<'w, AudioSettings>,
+    voices: Query<'w, 's, (), With<SfxVoice>>,
+    layout: Res<'w, Layout>,
+    court: Res<'w, CourtRoot>,
+}
+impl<'w, 's> Sfx<'w, 's> {
+    /// Plays a sound from `group` panned to `position` on the court, with its speed, and so
+    /// its pitch, multiplied by `pitch`.
+    pub fn play_at(&mut self, group: &str, position: Vec2, pitch: f32) {
+        if !self.manager.output_enabled {
+            return;
+        }
+        let volume = self.settings.bus_volume(self.settings.sfx_volume);
+        if volume <= 0. {
+            return;
+        }
+        if self.voices.iter().count() + self.manager.pending_voices >= self.settings.max_voices {
+            return;
+        }
+        let Some(group) = self.manager.groups.get(group) else {
+            return;
+        };
+        if group.variants.is_empty() {
+            return;
+        }
+        let mut thread_rng = rand::thread_rng();
+        let variant = group.variants[thread_rng.gen_range(0..group.variants.len())].clone();
+        let variation = if group.pitch_variation > 0. {
+            thread_rng.gen_range(-group.pitch_variation..group.pitch_variation)
+        } else {
+            0.
+        };
+        let settings = PlaybackSettings {
+            mode: PlaybackMode::Despawn,
+            volume: Volume::new(volume),
+            speed: (pitch * (1. + variation)).max(0.1),
+            spatial: self.settings.spatial,
+            //Only x matters so the pan is proportional to it across the screen
+            spatial_scale: Some(SpatialScale(vec3(1. / self.layout.view_size().x, 0., 0.))),
+            ..Default::default()
+        };
+        let transform =
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.)));
+        match variant {
+            SoundVariant::Sample(source) => {
+                self.commands
+                    .spawn((AudioBundle { source, settings }, transform, SfxVoice))
+                    .set_parent(self.court.0);
+            }
+            SoundVariant::Synth(source) => {
+                self.commands
+                    .spawn((AudioSourceBundle { source, settings }, transform, SfxVoice))
+                    .set_parent(self.court.0);
+            }
+        }
+        self.manager.pending_voices += 1;
+        self.manager.sounds_played += 1;
+    }
+}
+pub struct PongAudioPlugin;
+impl Plugin for PongAudioPlugin {
+    fn build(&self, app: &mut App) {
+        let output_enabled = app.is_plugin_added::<bevy::audio::AudioPlugin>();
+        app.insert_resource(load_config::<AudioSettings>(AUDIO_CONFIG))
+            .insert_resource(AudioManager {
+                output_enabled,
+                ..Default::default()
+            })
+            .add_systems(
+                Update,
+                (
+                    play_game_sounds.after(GameEventSet),
+                    audio_hotkeys,
+                    update_audio_debug_text,
+                ),
+            )
+            .add_systems(Last, reset_voice_counter);
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Master Volume".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(world.resource::<AudioSettings>().master_volume),
+                    adjust: |world, delta| {
+                        adjust_audio(world, |settings| {
+                            settings.master_volume = step_volume(settings.master_volume, delta)
+                        })
+                    },
+                },
+                order: 20,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "SFX Volume".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(world.resource::<AudioSettings>().sfx_volume),
+                    adjust: |world, delta| {
+                        adjust_audio(world, |settings| {
+                            settings.sfx_volume = step_volume(settings.sfx_volume, delta)
+                        })
+                    },
+                },
+                order: 21,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Music Volume".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(world.resource::<AudioSettings>().music_volume),
+                    adjust: |world, delta| {
+                        adjust_audio(world, |settings| {
+                            settings.music_volume = step_volume(settings.music_volume, delta)
+                        })
+                    },
+                },
+                order: 22,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Mute".into(),
+                action: MenuAction::Adjust {
+                    value: |world| on_off(world.resource::<AudioSettings>().muted),
+                    adjust: |world, _| {
+                        adjust_audio(world, |settings| settings.muted = !settings.muted)
+                    },
+                },
+                order: 23,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Sound Pack".into(),
+                action: MenuAction::Adjust {
+                    value: |world| world.resource::<AudioSettings>().sound_pack.clone(),
+                    adjust: |world, delta| {
+                        let packs = available_sound_packs();
+                        adjust_audio(world, |settings| {
+                            let current = packs
+                                .iter()
+                                .position(|pack| *pack == settings.sound_pack)
+                                .unwrap_or(0);
+                            let next = (current as i32 + delta).rem_euclid(packs.len() as i32);
+                            settings.sound_pack = packs[next as usize].clone();
+                        })
+                    },
+                },
+                order: 24,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Stereo Panning".into(),
+                action: MenuAction::Adjust {
+                    value: |world| on_off(world.resource::<AudioSettings>().spatial),
+                    adjust: |world, _| {
+                        adjust_audio(world, |settings| settings.spatial = !settings.spatial)
+                    },
+                },
+                order: 25,
+            },
+        );
+        if output_enabled {
+            app.add_audio_source::<SynthSound>()
+                .add_systems(Startup, spawn_listener)
+                .add_systems(Update, apply_sound_pack);
+        }
+    }
+}
+fn spawn_listener(mut commands: Commands) {
+    //Ears at the court edges so a sound at either goal plays on one side only
+    commands.spawn((
+        SpatialListener::new(PLAY_AREA.x),
+        TransformBundle::default(),
+    ));
+}
+fn apply_sound_pack(
+    asset_server: Res<AssetServer>,
+    mut synth_sounds: ResMut<Assets<SynthSound>>,
+    mut manager: ResMut<AudioManager>,
+    settings: Res<AudioSettings>,
+) {
+    if manager.loaded_pack.as_ref() == Some(&settings.sound_pack) {
+        return;
+    }
+    let pack = load_sound_pack(&settings.sound_pack);
+    manager.groups = pack
+        .groups
+        .into_iter()
+        .map(|(name, group)| {
+            let variants = group
+                .sounds
+                .into_iter()
+                .map(|entry| match entry {
+                    SoundPackEntry::Sample { path } => {
+                        SoundVariant::Sample(asset_server.load(path))
+                    }
+                    SoundPackEntry::Synth(preset) => {
+                        SoundVariant::Synth(synth_sounds.add(SynthSound(preset)))
+                    }
+                })
+                .collect();
+            (
+                name,
+                SoundGroup {
+                    variants,
+                    pitch_variation: group.pitch_variation,
+                },
+            )
+        })
+        .collect();
+    manager.loaded_pack = Some(settings.sound_pack.clone());
+}
+pub fn percent(volume: f32) -> String {
+    format!("{:.0}%", volume * 100.)
+}
+fn step_volume(volume: f32, delta: i32) -> f32 {
+    (volume + delta as f32 * VOLUME_STEP).clamp(0., 1.)
+}
+fn adjust_audio(world: &mut World, change: impl FnOnce(&mut AudioSettings)) {
+    let mut settings = world.resource_mut::<AudioSettings>();
+    change(&mut settings);
+    save_config(AUDIO_CONFIG, settings.as_ref());
+}
+fn reset_voice_counter(mut manager: ResMut<AudioManager>) {
+    manager.bypass_change_detection().pending_voices = 0;
+}
+fn play_game_sounds(
+    mut sfx: Sfx,
+    settings: Res<Settings>,
+    mut hits: EventReader<PaddleHit>,
+    mut bounces: EventReader<WallBounce>,
+    mut goals: EventReader<GoalScored>,
+) {
+    for hit in hits.read() {
+        //Faster returns sound higher, and off-centre hits bend the pitch a little
+        let pitch = (hit.speed / settings.max_spawn_speed.length())
+            .sqrt()
+            .clamp(0.75, 2.)
+            * (1. + hit.offset * 0.15);
+        sfx.play_at("hit", hit.position, pitch);
+    }
+    for bounce in bounces.read() {
+        sfx.play_at("bounce", bounce.position, 1.);
+    }
+    for goal in goals.read() {
+        sfx.play_at("death", goal.position, 1.);
+    }
+}
+fn audio_hotkeys(keyboard_input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<AudioSettings>) {
+    let mut changed = false;
+    if keyboard_input.just_pressed(KeyCode::KeyM) {
+        settings.muted = !settings.muted;
+        changed = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        settings.master_volume = step_volume(settings.master_volume, -1);
+        changed = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Equal) {
+        settings.master_volume = step_volume(settings.master_volume, 1);
+        changed = true;
+    }
+    if changed {
+        save_config(AUDIO_CONFIG, settings.as_ref());
+    }
+}
+fn update_audio_debug_text(
+    manager: Res<AudioManager>,
+    settings: Res<AudioSettings>,
+    mut debug_text: ResMut<DebugText>,
+) {
+    if manager.is_changed() {
+        change_debug_text(
+            &mut debug_text,
+            "Sounds Played",
+            &manager.sounds_played.to_string(),
+        );
+    }
+    if settings.is_changed() {
+        change_debug_text(
+            &mut debug_text,
+            "Volume",
+            &if settings.muted {
+                "Muted".to_string()
+            } else {
+                percent(settings.master_volume)
+            },
+        );
+    }
+}
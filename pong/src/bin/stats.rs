@@ -0,0 +1,3 @@
+fn main() {
+    pong::stats::run(std::env::args().skip(1));
+}
@@ -0,0 +1,1013 @@
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use common_modules::bitmap_font_plugin::{BitmapText, BitmapTextBundle};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::layout_plugin::Layout;
+use crate::match_stats::{reset_match, summary_input, MatchStats};
+use crate::menu_plugin::{
+    add_menu_entry, despawn_screen, spawn_screen, AppState, MenuAction, MenuEntry,
+};
+use crate::profiles::{type_name, Profiles};
+use crate::save_game::SAVE_DIR;
+
+const BRACKET_FILE: &str = "bracket.json";
+pub const MIN_ENTRANTS: usize = 4;
+pub const MAX_ENTRANTS: usize = 32;
+/// Rows of the round robin standings that fit on screen.
+const STANDINGS_SHOWN: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BracketFormat {
+    #[default]
+    SingleElimination,
+    DoubleElimination,
+    RoundRobin,
+}
+impl BracketFormat {
+    const ALL: [BracketFormat; 3] = [
+        BracketFormat::SingleElimination,
+        BracketFormat::DoubleElimination,
+        BracketFormat::RoundRobin,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            BracketFormat::SingleElimination => "Single Elimination",
+            BracketFormat::DoubleElimination => "Double Elimination",
+            BracketFormat::RoundRobin => "Round Robin",
+        }
+    }
+}
+/// Where a side of a match comes from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Entrant(usize),
+    /// The winner of an earlier match, by index.
+    Winner(usize),
+    /// The loser of an earlier match, for the losers bracket.
+    Loser(usize),
+    Bye,
+}
+/// Who fills a slot so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Seat {
+    Entrant(usize),
+    Bye,
+    /// Waiting on an earlier match.
+    Pending,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Winners,
+    Losers,
+    GrandFinal,
+    RoundRobin,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct MatchResult {
+    /// 0 when the left slot won, 1 for the right.
+    winner: usize,
+    scores: [u32; 2],
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct BracketMatch {
+    stage: Stage,
+    round: u32,
+    slots: [Slot; 2],
+    result: Option<MatchResult>,
+}
+#[derive(Debug, Clone, Default)]
+pub struct Standing {
+    pub entrant: usize,
+    pub wins: u32,
+    pub losses: u32,
+    pub points_for: u32,
+    pub points_against: u32,
+}
+/// A tournament between named entrants. Matches are kept in the order they should be played in,
+/// each fed by entrants or by the results of earlier matches.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bracket {
+    pub format: BracketFormat,
+    pub entrants: Vec<String>,
+    matches: Vec<BracketMatch>,
+}
+/// Seed order for an elimination bracket of `size` slots, so the top seeds meet last and get the
+/// byes.
+fn seeding(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let next = order.len() * 2;
+        order = order
+            .iter()
+            .flat_map(|&seed| [seed, next - 1 - seed])
+            .collect();
+    }
+    order
+}
+impl Bracket {
+    pub fn new(format: BracketFormat, entrants: Vec<String>) -> Self {
+        let mut bracket = Self {
+            format,
+            entrants,
+            matches: vec![],
+        };
+        match format {
+            BracketFormat::SingleElimination => bracket.build_elimination(false),
+            BracketFormat::DoubleElimination => bracket.build_elimination(true),
+            BracketFormat::RoundRobin => bracket.build_round_robin(),
+        }
+        bracket.settle_byes();
+        bracket
+    }
+    fn push(&mut self, stage: Stage, round: u32, slots: [Slot; 2]) -> Slot {
+        self.matches.push(BracketMatch {
+            stage,
+            round,
+            slots,
+            result: None,
+        });
+        Slot::Winner(self.matches.len() - 1)
+    }
+    /// Pairs up `slots` into the matches of one round, returning their winners.
+    fn push_round(&mut self, stage: Stage, round: u32, slots: &[Slot]) -> Vec<Slot> {
+        slots
+            .chunks(2)
+            .map(|pair| self.push(stage, round, [pair[0], pair[1]]))
+            .collect()
+    }
+    fn build_elimination(&mut self, double: bool) {
+        let count = self.entrants.len();
+        let seeds: Vec<Slot> = seeding(count.next_power_of_two().max(2))
+            .into_iter()
+            .map(|seed| {
+                if seed < count {
+                    Slot::Entrant(seed)
+                } else {
+                    Slot::Bye
+                }
+            })
+            .collect();
+        let mut winners = self.push_round(Stage::Winners, 1, &seeds);
+        let losers_of = |slots: &[Slot]| -> Vec<Slot> {
+            slots
+                .iter()
+                .map(|slot| match slot {
+                    Slot::Winner(index) => Slot::Loser(*index),
+                    _ => Slot::Bye,
+                })
+                .collect()
+        };
+        //The losers bracket starts with the first round losers playing each other, then
+        //alternates between taking in the losers of the next winners round and halving
+        let mut survivors = vec![];
+        if double && winners.len() > 1 {
+            survivors = self.push_round(Stage::Losers, 1, &losers_of(&winners));
+        }
+        let (mut round, mut losers_round) = (1, 1);
+        while winners.len() > 1 {
+            round += 1;
+            winners = self.push_round(Stage::Winners, round, &winners);
+            if !double {
+                continue;
+            }
+            //Drop losers in reversed so players don't meet the same opponent straight away
+            let mut dropped = losers_of(&winners);
+            dropped.reverse();
+            let pairs: Vec<Slot> = survivors
+                .iter()
+                .zip(dropped)
+                .flat_map(|(survivor, loser)| [*survivor, loser])
+                .collect();
+            losers_round += 1;
+            survivors = self.push_round(Stage::Losers, losers_round, &pairs);
+            if survivors.len() > 1 {
+                losers_round += 1;
+                survivors = self.push_round(Stage::Losers, losers_round, &survivors);
+            }
+        }
+        if double {
+            let final_slots = [winners[0], survivors.first().copied().unwrap_or(Slot::Bye)];
+            self.push(Stage::GrandFinal, 1, final_slots);
+            //The winners bracket champion hasn't lost yet, so losing the final only forces a
+            //rematch, which is skipped if they win the first one
+            self.push(Stage::GrandFinal, 2, final_slots);
+        }
+    }
+    /// Every entrant plays every other once, scheduled with the circle method.
+    fn build_round_robin(&mut self) {
+        let mut seats: Vec<Slot> = (0..self.entrants.len()).map(Slot::Entrant).collect();
+        if seats.len() % 2 == 1 {
+            seats.push(Slot::Bye);
+        }
+        let count = seats.len();
+        for round in 1..count as u32 {
+            for i in 0..count / 2 {
+                let pair = [seats[i], seats[count - 1 - i]];
+                if !pair.contains(&Slot::Bye) {
+                    self.push(Stage::RoundRobin, round, pair);
+                }
+            }
+            seats[1..].rotate_right(1);
+        }
+    }
+    fn seat(&self, slot: Slot) -> Seat {
+        let (index, winner) = match slot {
+            Slot::Entrant(entrant) => return Seat::Entrant(entrant),
+            Slot::Bye => return Seat::Bye,
+            Slot::Winner(index) => (index, true),
+            Slot::Loser(index) => (index, false),
+        };
+        let played = &self.matches[index];
+        match played.result {
+            Some(result) => {
+                let side = if winner {
+                    result.winner
+                } else {
+                    1 - result.winner
+                };
+                self.seat(played.slots[side])
+            }
+            None => Seat::Pending,
+        }
+    }
+    fn seats(&self, index: usize) -> [Seat; 2] {
+        self.matches[index].slots.map(|slot| self.seat(slot))
+    }
+    /// Whether the match is the grand final reset and isn't needed, because the winners bracket
+    /// champion won the first final.
+    fn reset_skipped(&self, index: usize) -> bool {
+        let played = &self.matches[index];
+        played.stage == Stage::GrandFinal
+            && played.round == 2
+            && self.matches[index - 1]
+                .result
+                .is_some_and(|first| first.winner == 0)
+    }
+    /// Settles every match with a bye, which the other side wins without playing, and a grand
+    /// final reset that isn't needed.
+    fn settle_byes(&mut self) {
+        loop {
+            let walkover = (0..self.matches.len()).find_map(|index| {
+                if self.matches[index].result.is_some() {
+                    return None;
+                }
+                if self.reset_skipped(index) {
+                    return Some((index, 0));
+                }
+                match self.seats(index) {
+                    [Seat::Bye, Seat::Entrant(_)] => Some((index, 1)),
+                    [Seat::Entrant(_) | Seat::Bye, Seat::Bye] => Some((index, 0)),
+                    _ => None,
+                }
+            });
+            let Some((index, winner)) = walkover else {
+                return;
+            };
+            self.matches[index].result = Some(MatchResult {
+                winner,
+                scores: [0, 0],
+            });
+        }
+    }
+    /// The entrants of a match that is ready to be played.
+    pub fn players(&self, index: usize) -> Option<[usize; 2]> {
+        match self.seats(index) {
+            [Seat::Entrant(left), Seat::Entrant(right)] => Some([left, right]),
+            _ => None,
+        }
+    }
+    /// The first match that is ready and has not been played.
+    pub fn next_match(&self) -> Option<usize> {
+        (0..self.matches.len())
+            .find(|index| self.matches[*index].result.is_none() && self.players(*index).is_some())
+    }
+    /// Records a played match. A tie, which a finished match can't end in, goes to the left slot.
+    pub fn record(&mut self, index: usize, scores: [u32; 2]) {
+        let winner = if scores[0] >= scores[1] { 0 } else { 1 };
+        self.matches[index].result = Some(MatchResult { winner, scores });
+        self.settle_byes();
+    }
+    pub fn champion(&self) -> Option<usize> {
+        if self.format == BracketFormat::RoundRobin {
+            if self.matches.iter().any(|played| played.result.is_none()) {
+                return None;
+            }
+            return self.standings().first().map(|standing| standing.entrant);
+        }
+        let last = self.matches.len().checked_sub(1)?;
+        match self.seat(Slot::Winner(last)) {
+            Seat::Entrant(entrant) => Some(entrant),
+            _ => None,
+        }
+    }
+    /// Wins and points from every match played, best first.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = (0..self.entrants.len())
+            .map(|entrant| Standing {
+                entrant,
+                ..Default::default()
+            })
+            .collect();
+        for (index, played) in self.matches.iter().enumerate() {
+            let (Some(result), Some(players)) = (played.result, self.players(index)) else {
+                continue;
+            };
+            if self.reset_skipped(index) {
+                continue;
+            }
+            for side in 0..2 {
+                let standing = &mut standings[players[side]];
+                standing.points_for += result.scores[side];
+                standing.points_against += result.scores[1 - side];
+                if side == result.winner {
+                    standing.wins += 1;
+                } else {
+                    standing.losses += 1;
+                }
+            }
+        }
+        standings.sort_by_key(|standing| {
+            std::cmp::Reverse((
+                standing.wins,
+                standing.points_for as i64 - standing.points_against as i64,
+            ))
+        });
+        standings
+    }
+    /// Each stage and round in play order.
+    fn rounds(&self) -> Vec<(Stage, u32)> {
+        let mut rounds = vec![];
+        for played in &self.matches {
+            if !rounds.contains(&(played.stage, played.round)) {
+                rounds.push((played.stage, played.round));
+            }
+        }
+        rounds
+    }
+    fn round_name(stage: Stage, round: u32) -> String {
+        match stage {
+            Stage::Winners => format!("ROUND {round}"),
+            Stage::Losers => format!("LOSERS ROUND {round}"),
+            Stage::GrandFinal if round > 1 => "GRAND FINAL RESET".into(),
+            Stage::GrandFinal => "GRAND FINAL".into(),
+            Stage::RoundRobin => format!("ROUND {round}"),
+        }
+    }
+    fn seat_name(&self, seat: Seat) -> &str {
+        match seat {
+            Seat::Entrant(entrant) => &self.entrants[entrant],
+            Seat::Bye => "BYE",
+            Seat::Pending => "?",
+        }
+    }
+    fn match_line(&self, index: usize) -> String {
+        let [left, right] = self.seats(index);
+        let score = match self.matches[index].result {
+            Some(_) if left == Seat::Bye || right == Seat::Bye || self.reset_skipped(index) => {
+                "-".to_string()
+            }
+            Some(result) => format!("{}-{}", result.scores[0], result.scores[1]),
+            None => "VS".into(),
+        };
+        format!(
+            "{:>10} {:^7} {:<10}",
+            self.seat_name(left),
+            score,
+            self.seat_name(right)
+        )
+    }
+}
+/// The tournament in progress, saved after every change so it survives restarts.
+#[derive(Resource, Serialize, Deserialize, Default)]
+struct Tournament {
+    bracket: Option<Bracket>,
+    /// The match being played, by index.
+    playing: Option<usize>,
+    /// Round shown on the bracket screen.
+    #[serde(skip)]
+    page: usize,
+    /// Set when the match about to be played should start from 0-0.
+    #[serde(skip)]
+    starting: bool,
+    /// Set when a bracket match just ended, so the summary returns to the bracket.
+    #[serde(skip)]
+    finished: bool,
+    /// Set while the match on the court was started from the bracket. Cleared on going back to
+    /// the menu, so a match played from there isn't taken as the bracket result.
+    #[serde(skip)]
+    in_play: bool,
+}
+impl Tournament {
+    fn path() -> PathBuf {
+        PathBuf::from(SAVE_DIR).join(BRACKET_FILE)
+    }
+    fn load() -> Self {
+        let path = Self::path();
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|err| {
+            eprintln!("Error: failed to parse {:?}: {err}", path);
+            Self::default()
+        })
+    }
+    fn save(&self) {
+        let written = serde_json::to_string_pretty(self)
+            .map_err(std::io::Error::other)
+            .and_then(|json| {
+                fs::create_dir_all(SAVE_DIR)?;
+                fs::write(Self::path(), json)
+            });
+        if let Err(err) = written {
+            eprintln!("Error: failed to save the bracket: {err}");
+        }
+    }
+    fn clear(&mut self) {
+        *self = Self::default();
+        let path = Self::path();
+        if path.exists() {
+            if let Err(err) = fs::remove_file(&path) {
+                eprintln!("Error: failed to remove {:?}: {err}", path);
+            }
+        }
+    }
+}
+/// Players entered so far for a new bracket.
+#[derive(Resource, Default)]
+struct BracketSetup {
+    format: BracketFormat,
+    name: String,
+    entrants: Vec<String>,
+}
+#[derive(Component)]
+struct BracketScreen;
+#[derive(Component)]
+struct BracketText;
+pub struct BracketPlugin;
+impl Plugin for BracketPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Tournament::load())
+            .init_resource::<BracketSetup>()
+            .add_systems(OnEnter(AppState::BracketSetup), spawn_bracket_screen)
+            .add_systems(
+                Update,
+                (setup_input, update_setup_text)
+                    .chain()
+                    .run_if(in_state(AppState::BracketSetup)),
+            )
+            .add_systems(
+                OnExit(AppState::BracketSetup),
+                despawn_screen::<BracketScreen>,
+            )
+            .add_systems(
+                OnEnter(AppState::Bracket),
+                (show_next_round, spawn_bracket_screen),
+            )
+            .add_systems(
+                Update,
+                (bracket_input, update_bracket_text)
+                    .chain()
+                    .run_if(in_state(AppState::Bracket)),
+            )
+            .add_systems(
+                OnExit(AppState::Bracket),
+                (
+                    despawn_screen::<BracketScreen>,
+                    (reset_match, |mut tournament: ResMut<Tournament>| {
+                        tournament.starting = false
+                    })
+                        .chain()
+                        .run_if(|tournament: Res<Tournament>| tournament.starting),
+                ),
+            )
+            .add_systems(OnEnter(AppState::Summary), record_result)
+            .add_systems(
+                OnEnter(AppState::Menu),
+                |mut tournament: ResMut<Tournament>| tournament.in_play = false,
+            )
+            .add_systems(
+                Update,
+                back_to_bracket
+                    .after(summary_input)
+                    .run_if(in_state(AppState::Summary)),
+            );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Bracket".into(),
+                action: MenuAction::Activate(|world| {
+                    let next = if world.resource::<Tournament>().bracket.is_some() {
+                        AppState::Bracket
+                    } else {
+                        *world.resource_mut::<BracketSetup>() = BracketSetup::default();
+                        AppState::BracketSetup
+                    };
+                    world.resource_mut::<NextState<AppState>>().set(next);
+                }),
+                order: 12,
+            },
+        );
+    }
+}
+fn spawn_bracket_screen(mut commands: Commands, layout: Res<Layout>) {
+    let view_size = layout.view_size();
+    let screen = spawn_screen(&mut commands, view_size, BracketScreen, "BRACKET", 8.);
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn((
+            BitmapTextBundle::new(BitmapText::new("", 2.), vec3(0., -20., 1.)),
+            BracketText,
+        ));
+    });
+}
+fn setup_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut setup: ResMut<BracketSetup>,
+    mut tournament: ResMut<Tournament>,
+    profiles: Res<Profiles>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        let all = BracketFormat::ALL;
+        let index = all.iter().position(|format| *format == setup.format);
+        setup.format = all[index.map_or(0, |index| (index + 1) % all.len())];
+    }
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        for profile in &profiles.profiles {
+            if setup.entrants.len() < MAX_ENTRANTS && !setup.entrants.contains(&profile.name) {
+                setup.entrants.push(profile.name.clone());
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        //Backspace on an empty name takes back the last entrant
+        if setup.name.pop().is_none() {
+            setup.entrants.pop();
+        }
+    }
+    let mut name = std::mem::take(&mut setup.name);
+    type_name(&mut name, &mut characters);
+    setup.name = name;
+    if !keyboard_input.just_pressed(KeyCode::Enter) {
+        return;
+    }
+    let name = setup.name.trim().to_string();
+    if name.is_empty() {
+        if setup.entrants.len() >= MIN_ENTRANTS {
+            let entrants = std::mem::take(&mut setup.entrants);
+            *tournament = Tournament {
+                bracket: Some(Bracket::new(setup.format, entrants)),
+                ..Default::default()
+            };
+            tournament.save();
+            next_state.set(AppState::Bracket);
+        }
+    } else if setup.entrants.len() < MAX_ENTRANTS && !setup.entrants.contains(&name) {
+        setup.entrants.push(name);
+        setup.name.clear();
+    }
+}
+fn update_setup_text(
+    setup: Res<BracketSetup>,
+    mut texts: Query<&mut BitmapText, With<BracketText>>,
+) {
+    if !setup.is_changed() {
+        return;
+    }
+    let mut entrants = String::new();
+    for (i, chunk) in setup.entrants.chunks(4).enumerate() {
+        if i > 0 {
+            entrants.push('\n');
+        }
+        entrants.push_str(&chunk.join(", "));
+    }
+    let text = format!(
+        "FORMAT: {}\n\nNAME: {}_\n\nPLAYERS ({}/{MAX_ENTRANTS}):\n{entrants}\n\n\
+        ENTER: ADD NAME, OR START WITH AN EMPTY NAME\n\
+        TAB: FORMAT  F1: ADD ALL PROFILES\n\
+        BACKSPACE: REMOVE  ESC: MENU",
+        setup.format.name(),
+        setup.name,
+        setup.entrants.len(),
+    );
+    for mut bitmap_text in texts.iter_mut() {
+        bitmap_text.text = text.clone();
+    }
+}
+/// Opens the bracket on the round of the next match.
+fn show_next_round(mut tournament: ResMut<Tournament>) {
+    let Some(bracket) = &tournament.bracket else {
+        return;
+    };
+    let current = tournament.playing.or_else(|| bracket.next_match());
+    let page = current.map_or(0, |index| {
+        let played = &bracket.matches[index];
+        bracket
+            .rounds()
+            .iter()
+            .position(|round| *round == (played.stage, played.round))
+            .unwrap_or(0)
+    });
+    tournament.page = page;
+}
+fn bracket_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut tournament: ResMut<Tournament>,
+    mut profiles: ResMut<Profiles>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(bracket) = &tournament.bracket else {
+        next_state.set(AppState::Menu);
+        return;
+    };
+    let pages = bracket.rounds().len() + 1;
+    let next = bracket.next_match();
+    let champion = bracket.champion();
+    //Rate the next match when the players have profiles
+    let next_profiles = next
+        .and_then(|index| bracket.players(index))
+        .map(|players| {
+            players.map(|entrant| {
+                let name = &bracket.entrants[entrant];
+                profiles.get(name).map(|_| name.clone())
+            })
+        });
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft) || keyboard_input.just_pressed(KeyCode::KeyA)
+    {
+        tournament.page = (tournament.page + pages - 1) % pages;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowRight)
+        || keyboard_input.just_pressed(KeyCode::KeyD)
+    {
+        tournament.page = (tournament.page + 1) % pages;
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+    } else if keyboard_input.just_pressed(KeyCode::KeyX) {
+        tournament.clear();
+        next_state.set(AppState::Menu);
+    } else if keyboard_input.just_pressed(KeyCode::Enter) {
+        if champion.is_some() {
+            tournament.clear();
+            next_state.set(AppState::Menu);
+        } else if tournament.playing.is_some() {
+            tournament.in_play = true;
+            next_state.set(AppState::Playing);
+        } else if let Some(index) = next {
+            if let Some([left, right]) = next_profiles {
+                profiles.left = left;
+                profiles.right = right;
+                profiles.save();
+            }
+            tournament.playing = Some(index);
+            tournament.starting = true;
+            tournament.in_play = true;
+            tournament.save();
+            next_state.set(AppState::Playing);
+        }
+    }
+}
+fn bracket_text(tournament: &Tournament) -> String {
+    let Some(bracket) = &tournament.bracket else {
+        return String::new();
+    };
+    let rounds = bracket.rounds();
+    let mut text = format!("{}\n", bracket.format.name());
+    match rounds.get(tournament.page) {
+        Some(&(stage, round)) => {
+            text.push_str(&format!(
+                "{} ({}/{})\n\n",
+                Bracket::round_name(stage, round),
+                tournament.page + 1,
+                rounds.len() + 1
+            ));
+            for (index, played) in bracket.matches.iter().enumerate() {
+                if (played.stage, played.round) == (stage, round) {
+                    text.push_str(&bracket.match_line(index));
+                    text.push('\n');
+                }
+            }
+        }
+        None => {
+            text.push_str(&format!(
+                "STANDINGS ({}/{})\n\n{:<3}{:<11}{:>4}{:>4}{:>6}\n",
+                rounds.len() + 1,
+                rounds.len() + 1,
+                "#",
+                "NAME",
+                "W",
+                "L",
+                "+/-"
+            ));
+            for (rank, standing) in bracket.standings().iter().take(STANDINGS_SHOWN).enumerate() {
+                text.push_str(&format!(
+                    "{:<3}{:<11}{:>4}{:>4}{:>6}\n",
+                    rank + 1,
+                    bracket.entrants[standing.entrant],
+                    standing.wins,
+                    standing.losses,
+                    standing.points_for as i64 - standing.points_against as i64
+                ));
+            }
+        }
+    }
+    text.push('\n');
+    let players = |index: usize| {
+        bracket
+            .players(index)
+            .map(|[left, right]| {
+                format!("{} VS {}", bracket.entrants[left], bracket.entrants[right])
+            })
+            .unwrap_or_default()
+    };
+    if let Some(champion) = bracket.champion() {
+        text.push_str(&format!(
+            "CHAMPION: {}\nENTER: FINISH",
+            bracket.entrants[champion]
+        ));
+    } else if let Some(index) = tournament.playing {
+        text.push_str(&format!("PLAYING: {}\nENTER: RESUME", players(index)));
+    } else if let Some(index) = bracket.next_match() {
+        text.push_str(&format!("NEXT: {}\nENTER: PLAY", players(index)));
+    }
+    text.push_str("  LEFT/RIGHT: ROUNDS  X: ABANDON  ESC: MENU");
+    text
+}
+fn update_bracket_text(
+    tournament: Res<Tournament>,
+    mut texts: Query<&mut BitmapText, With<BracketText>>,
+) {
+    if !tournament.is_changed() {
+        return;
+    }
+    let text = bracket_text(&tournament);
+    for mut bitmap_text in texts.iter_mut() {
+        bitmap_text.text = text.clone();
+    }
+}
+fn record_result(mut tournament: ResMut<Tournament>, stats: Res<MatchStats>) {
+    if !std::mem::take(&mut tournament.in_play) {
+        return;
+    }
+    let Some(index) = tournament.playing.take() else {
+        return;
+    };
+    if let Some(bracket) = &mut tournament.bracket {
+        bracket.record(index, [stats.score.left, stats.score.right]);
+    }
+    tournament.finished = true;
+    tournament.save();
+}
+/// Bracket matches go back to the bracket rather than to a rematch or the menu.
+fn back_to_bracket(
+    mut tournament: ResMut<Tournament>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if tournament.finished && next_state.0.is_some() {
+        tournament.finished = false;
+        next_state.set(AppState::Bracket);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [usize; 4] = [4, 5, 8, 32];
+
+    fn bracket(format: BracketFormat, count: usize) -> Bracket {
+        Bracket::new(format, (0..count).map(|i| format!("P{i}")).collect())
+    }
+
+    /// Plays every match to the end, `pick` choosing the winning side from the two entrants.
+    /// Returns how many matches were played.
+    fn play_out(bracket: &mut Bracket, pick: impl Fn([usize; 2]) -> usize) -> usize {
+        let mut played = 0;
+        while let Some(index) = bracket.next_match() {
+            let players = bracket.players(index).unwrap();
+            let scores = if pick(players) == 0 { [5, 2] } else { [2, 5] };
+            bracket.record(index, scores);
+            played += 1;
+        }
+        played
+    }
+
+    fn top_seed(players: [usize; 2]) -> usize {
+        if players[0] < players[1] {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn losses(bracket: &Bracket) -> Vec<u32> {
+        let mut losses = vec![0; bracket.entrants.len()];
+        for standing in bracket.standings() {
+            losses[standing.entrant] = standing.losses;
+        }
+        losses
+    }
+
+    #[test]
+    fn seeding_keeps_top_seeds_apart() {
+        assert_eq!(seeding(4), [0, 3, 1, 2]);
+        assert_eq!(seeding(8), [0, 7, 3, 4, 1, 6, 2, 5]);
+        let order = seeding(32);
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..32).collect::<Vec<_>>());
+        for pair in order.chunks(2) {
+            assert_eq!(pair[0] + pair[1], 31);
+        }
+        //The top two seeds are in opposite halves
+        assert!(order[..16].contains(&0) && order[16..].contains(&1));
+    }
+
+    #[test]
+    fn single_elimination_gives_byes_to_top_seeds() {
+        for count in SIZES {
+            let bracket = bracket(BracketFormat::SingleElimination, count);
+            let size = count.next_power_of_two();
+            assert_eq!(bracket.matches.len(), size - 1);
+            let settled: Vec<usize> = (0..bracket.matches.len())
+                .filter(|index| bracket.matches[*index].result.is_some())
+                .collect();
+            assert_eq!(settled.len(), size - count);
+            for index in settled {
+                let played = &bracket.matches[index];
+                assert_eq!(played.round, 1);
+                let result = played.result.unwrap();
+                assert_eq!(played.slots[1 - result.winner], Slot::Bye);
+                //Byes go to the seeds at the top
+                assert!(matches!(
+                    played.slots[result.winner],
+                    Slot::Entrant(seed) if seed < size - count
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn single_elimination_plays_out() {
+        for count in SIZES {
+            let mut bracket = bracket(BracketFormat::SingleElimination, count);
+            assert_eq!(bracket.champion(), None);
+            assert_eq!(play_out(&mut bracket, top_seed), count - 1);
+            assert_eq!(bracket.champion(), Some(0));
+            let losses = losses(&bracket);
+            assert_eq!(losses[0], 0);
+            assert!(losses[1..].iter().all(|lost| *lost == 1));
+        }
+    }
+
+    #[test]
+    fn double_elimination_knocks_out_after_two_losses() {
+        for count in SIZES {
+            let mut bracket = bracket(BracketFormat::DoubleElimination, count);
+            let size = count.next_power_of_two();
+            //Winners and losers brackets, the grand final and its reset
+            assert_eq!(bracket.matches.len(), (size - 1) + (size - 2) + 2);
+            assert_eq!(play_out(&mut bracket, top_seed), 2 * (count - 1));
+            assert_eq!(bracket.champion(), Some(0));
+            let losses = losses(&bracket);
+            assert_eq!(losses[0], 0);
+            assert!(losses[1..].iter().all(|lost| *lost == 2));
+            let reset = bracket.matches.len() - 1;
+            assert!(bracket.reset_skipped(reset));
+        }
+    }
+
+    #[test]
+    fn double_elimination_resets_when_the_winners_champion_loses_the_final() {
+        for count in SIZES {
+            let mut bracket = bracket(BracketFormat::DoubleElimination, count);
+            let first_final = bracket.matches.len() - 2;
+            //Seed 0 wins everything but the first final
+            while let Some(index) = bracket.next_match() {
+                let players = bracket.players(index).unwrap();
+                let winner = if index == first_final {
+                    1
+                } else {
+                    top_seed(players)
+                };
+                bracket.record(index, if winner == 0 { [5, 2] } else { [2, 5] });
+                if index == first_final {
+                    assert_eq!(bracket.champion(), None);
+                    assert_eq!(bracket.next_match(), Some(index + 1));
+                    assert_eq!(bracket.players(index + 1), Some(players));
+                }
+            }
+            assert_eq!(bracket.champion(), Some(0));
+            assert_eq!(losses(&bracket)[0], 1);
+        }
+    }
+
+    #[test]
+    fn round_robin_pairs_everyone_once() {
+        for count in SIZES {
+            let bracket = bracket(BracketFormat::RoundRobin, count);
+            assert_eq!(bracket.matches.len(), count * (count - 1) / 2);
+            let mut met = vec![vec![0; count]; count];
+            for index in 0..bracket.matches.len() {
+                let [left, right] = bracket.players(index).unwrap();
+                met[left][right] += 1;
+                met[right][left] += 1;
+            }
+            for (entrant, opponents) in met.iter().enumerate() {
+                for (opponent, times) in opponents.iter().enumerate() {
+                    assert_eq!(*times, (entrant != opponent) as u32);
+                }
+            }
+            //Nobody plays twice in a round
+            for (stage, round) in bracket.rounds() {
+                let mut seen = vec![];
+                for (index, played) in bracket.matches.iter().enumerate() {
+                    if (played.stage, played.round) == (stage, round) {
+                        for entrant in bracket.players(index).unwrap() {
+                            assert!(!seen.contains(&entrant));
+                            seen.push(entrant);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_robin_standings_and_champion() {
+        for count in SIZES {
+            let mut bracket = bracket(BracketFormat::RoundRobin, count);
+            let last = bracket.matches.len() - 1;
+            for index in 0..last {
+                let players = bracket.players(index).unwrap();
+                bracket.record(
+                    index,
+                    if top_seed(players) == 0 {
+                        [5, 2]
+                    } else {
+                        [2, 5]
+                    },
+                );
+            }
+            assert_eq!(bracket.champion(), None);
+            let players = bracket.players(last).unwrap();
+            bracket.record(
+                last,
+                if top_seed(players) == 0 {
+                    [5, 2]
+                } else {
+                    [2, 5]
+                },
+            );
+            assert_eq!(bracket.next_match(), None);
+            assert_eq!(bracket.champion(), Some(0));
+            let standings = bracket.standings();
+            for (rank, standing) in standings.iter().enumerate() {
+                assert_eq!(standing.entrant, rank);
+                assert_eq!(standing.wins as usize, count - 1 - rank);
+                assert_eq!(standing.losses as usize, rank);
+                let played = count as u32 - 1;
+                assert_eq!(standing.points_for + standing.points_against, played * 7);
+            }
+        }
+    }
+
+    #[test]
+    fn standings_break_ties_on_points() {
+        let mut bracket = bracket(BracketFormat::RoundRobin, 4);
+        //0, 1 and 2 beat each other in a circle and all beat 3, 2 by the widest margin
+        while let Some(index) = bracket.next_match() {
+            let [left, right] = bracket.players(index).unwrap();
+            let beats = |a: usize, b: usize| b == 3 || (a != 3 && (a + 1) % 3 == b);
+            let winner = if beats(left, right) { 0 } else { 1 };
+            let margin = if [left, right] == [2, 3] || [left, right] == [3, 2] {
+                5
+            } else {
+                3
+            };
+            let mut scores = [5; 2];
+            scores[1 - winner] -= margin;
+            bracket.record(index, scores);
+        }
+        let standings = bracket.standings();
+        assert_eq!(standings[0].entrant, 2);
+        assert_eq!(standings[3].entrant, 3);
+        assert!(standings[..3].iter().all(|standing| standing.wins == 2));
+    }
+
+    #[test]
+    fn tie_goes_to_the_left_slot() {
+        let mut bracket = bracket(BracketFormat::SingleElimination, 4);
+        let index = bracket.next_match().unwrap();
+        let [left, _] = bracket.players(index).unwrap();
+        bracket.record(index, [3, 3]);
+        assert_eq!(bracket.seat(Slot::Winner(index)), Seat::Entrant(left));
+    }
+}
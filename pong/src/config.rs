@@ -0,0 +1,40 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_DIR: &str = "./config";
+
+fn config_path(name: &str) -> PathBuf {
+    Path::new(CONFIG_DIR).join(format!("{name}.json"))
+}
+/// Reads `config/<name>.json`, falling back to the default when it is missing or invalid.
+pub fn load_config<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = config_path(name);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return T::default(),
+    };
+    match serde_json::from_str(&contents) {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("Error: failed to parse {:?}: {err}", path);
+            T::default()
+        }
+    }
+}
+pub fn save_config<T: Serialize>(name: &str, value: &T) {
+    let path = config_path(name);
+    if let Err(err) = fs::create_dir_all(CONFIG_DIR) {
+        eprintln!("Error: failed to create {CONFIG_DIR}: {err}");
+        return;
+    }
+    match serde_json::to_string_pretty(value) {
+        Ok(contents) => {
+            if let Err(err) = fs::write(&path, contents) {
+                eprintln!("Error: failed to write {:?}: {err}", path);
+            }
+        }
+        Err(err) => eprintln!("Error: failed to serialize {:?}: {err}", path),
+    }
+}
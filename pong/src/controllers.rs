@@ -0,0 +1,408 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{IpAddr, UdpSocket};
+use std::path::PathBuf;
+
+use crate::layout_plugin::Orientation;
+use crate::paddle_control::{
+    Controller, ControllerContext, ControllerKind, PaddleController, PaddleInput,
+};
+use crate::{Paddle, Player, PLAY_AREA};
+
+const REPLAY_DIR: &str = "./replays";
+const REMOTE_BASE_PORT: u16 = 7400;
+
+pub struct KeyboardController {
+    up: KeyCode,
+    down: KeyCode,
+    //Keys that move the paddle up the court when it is laid out vertically
+    portrait_up: KeyCode,
+    portrait_down: KeyCode,
+}
+impl KeyboardController {
+    pub fn for_player(player: Player) -> Self {
+        match player {
+            Player::Left => Self {
+                up: KeyCode::KeyW,
+                down: KeyCode::KeyS,
+                portrait_up: KeyCode::KeyD,
+                portrait_down: KeyCode::KeyA,
+            },
+            Player::Right => Self {
+                up: KeyCode::ArrowUp,
+                down: KeyCode::ArrowDown,
+                portrait_up: KeyCode::ArrowRight,
+                portrait_down: KeyCode::ArrowLeft,
+            },
+        }
+    }
+}
+impl PaddleController for KeyboardController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Keyboard
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        let (up, down) = match context.orientation {
+            Orientation::Landscape => (self.up, self.down),
+            Orientation::Portrait => (self.portrait_up, self.portrait_down),
+        };
+        let mut direction = 0.;
+        if context.keyboard.pressed(up) {
+            direction += 1.;
+        }
+        if context.keyboard.pressed(down) {
+            direction -= 1.;
+        }
+        PaddleInput::direction(direction)
+    }
+}
+pub struct MouseController;
+impl PaddleController for MouseController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Mouse
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        match context.cursor {
+            Some(cursor) => PaddleInput::target(cursor.y),
+            None => PaddleInput::default(),
+        }
+    }
+}
+/// Follows the most recent finger on the player's half of the court.
+pub struct TouchController;
+impl PaddleController for TouchController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Touch
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        context
+            .touches
+            .iter()
+            .rev()
+            .find(|position| (position.x < 0.) == (context.player == Player::Left))
+            .map(|position| PaddleInput::target(position.y))
+            .unwrap_or_default()
+    }
+}
+/// Left stick or d-pad of the first gamepad for the left player and the second for the right,
+/// falling back to the first when only one is connected.
+pub struct GamepadController;
+impl PaddleController for GamepadController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Gamepad
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        let gamepads = context.gamepad.gamepads;
+        let index = match context.player {
+            Player::Left => 0,
+            Player::Right => 1,
+        };
+        let Some(gamepad) = gamepads.iter().nth(index).or(gamepads.iter().next()) else {
+            return PaddleInput::default();
+        };
+        let (axis, up, down) = match context.orientation {
+            Orientation::Landscape => (
+                GamepadAxisType::LeftStickY,
+                GamepadButtonType::DPadUp,
+                GamepadButtonType::DPadDown,
+            ),
+            Orientation::Portrait => (
+                GamepadAxisType::LeftStickX,
+                GamepadButtonType::DPadRight,
+                GamepadButtonType::DPadLeft,
+            ),
+        };
+        let buttons = context.gamepad.buttons;
+        let mut direction = context
+            .gamepad
+            .axes
+            .get(GamepadAxis::new(gamepad, axis))
+            .unwrap_or(0.);
+        if buttons.pressed(GamepadButton::new(gamepad, up)) {
+            direction += 1.;
+        }
+        if buttons.pressed(GamepadButton::new(gamepad, down)) {
+            direction -= 1.;
+        }
+        PaddleInput::direction(direction)
+    }
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AiDifficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+impl AiDifficulty {
+    pub const ALL: [AiDifficulty; 3] =
+        [AiDifficulty::Easy, AiDifficulty::Normal, AiDifficulty::Hard];
+    pub fn name(&self) -> &'static str {
+        match self {
+            AiDifficulty::Easy => "Easy",
+            AiDifficulty::Normal => "Normal",
+            AiDifficulty::Hard => "Hard",
+        }
+    }
+    /// Seconds between re-aims, and the largest aiming error as a fraction of paddle height.
+    fn skill(&self) -> (f32, f32) {
+        match self {
+            AiDifficulty::Easy => (0.3, 0.8),
+            AiDifficulty::Normal => (0.15, 0.4),
+            AiDifficulty::Hard => (0.08, 0.2),
+        }
+    }
+}
+/// Predicts where the ball will cross its paddle, re-aiming every `reaction` seconds with a
+/// random error so it can be beaten.
+pub struct AiController {
+    reaction: f32,
+    error: f32,
+    since_aim: f32,
+    target: f32,
+    rng: StdRng,
+}
+impl AiController {
+    pub fn new(difficulty: AiDifficulty, seed: u64) -> Self {
+        let (reaction, error) = difficulty.skill();
+        Self {
+            reaction,
+            error,
+            since_aim: f32::INFINITY,
+            target: 0.,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+impl PaddleController for AiController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Ai
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        self.since_aim += context.delta_seconds;
+        if self.since_aim >= self.reaction {
+            self.since_aim = 0.;
+            let paddle_x = match context.player {
+                Player::Left => -context.settings.paddle_x,
+                Player::Right => context.settings.paddle_x,
+            };
+            self.target = match context.ball {
+                Some(ball) if (paddle_x - ball.position.x) * ball.velocity.x > 0. => {
+                    let half_height = (PLAY_AREA.y - context.settings.ball_size) / 2.;
+                    let error = context.settings.player_paddle_size(context.player).y * self.error;
+                    predict_crossing(ball.position, ball.velocity, paddle_x, half_height)
+                        + self.rng.gen_range(-error..=error)
+                }
+                _ => 0.,
+            };
+        }
+        PaddleInput::target(self.target)
+    }
+}
+/// The y where a ball bouncing between walls at ±`half_height` reaches `x`.
+pub fn predict_crossing(position: Vec2, velocity: Vec2, x: f32, half_height: f32) -> f32 {
+    if velocity.x == 0. || half_height <= 0. {
+        return position.y;
+    }
+    let time = (x - position.x) / velocity.x;
+    let unfolded = position.y + velocity.y * time + half_height;
+    let period = half_height * 4.;
+    let folded = unfolded.rem_euclid(period);
+    if folded > half_height * 2. {
+        period - folded - half_height
+    } else {
+        folded - half_height
+    }
+}
+/// Takes its input from UDP datagrams holding a JSON [`PaddleInput`], on port 7400 for the left
+/// paddle and 7401 for the right of `address`. Holds the last input received.
+pub struct RemoteController {
+    socket: Option<UdpSocket>,
+    input: PaddleInput,
+}
+impl RemoteController {
+    pub fn bind(player: Player, address: IpAddr) -> Self {
+        let port = REMOTE_BASE_PORT + player as u16;
+        let socket = UdpSocket::bind((address, port))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+        let socket = match socket {
+            Ok(socket) => Some(socket),
+            Err(err) => {
+                eprintln!("Error: failed to listen for remote input on port {port}: {err}");
+                None
+            }
+        };
+        Self {
+            socket,
+            input: PaddleInput::default(),
+        }
+    }
+}
+impl PaddleController for RemoteController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Remote
+    }
+    fn update(&mut self, _context: &ControllerContext) -> PaddleInput {
+        let Some(socket) = &self.socket else {
+            return PaddleInput::default();
+        };
+        let mut buffer = [0; 512];
+        while let Ok(length) = socket.recv(&mut buffer) {
+            match serde_json::from_slice::<PaddleInput>(&buffer[..length]) {
+                Ok(input) => self.input = input,
+                Err(err) => eprintln!("Error: bad remote input: {err}"),
+            }
+        }
+        self.input
+    }
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct RecordedInput {
+    time: f32,
+    input: PaddleInput,
+}
+/// Loops the inputs recorded for this side during the last match.
+pub struct ReplayController {
+    inputs: Vec<RecordedInput>,
+    time: f32,
+    index: usize,
+}
+impl ReplayController {
+    pub fn load(player: Player) -> Self {
+        let path = replay_path(player);
+        let inputs = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                eprintln!("Error: failed to parse {:?}: {err}", path);
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        Self {
+            inputs,
+            time: 0.,
+            index: 0,
+        }
+    }
+}
+impl PaddleController for ReplayController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Replay
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        let Some(last) = self.inputs.last() else {
+            return PaddleInput::default();
+        };
+        self.time += context.delta_seconds;
+        if self.time > last.time {
+            self.time = 0.;
+            self.index = 0;
+        }
+        while self.index + 1 < self.inputs.len() && self.inputs[self.index + 1].time <= self.time {
+            self.index += 1;
+        }
+        self.inputs[self.index].input
+    }
+}
+fn replay_path(player: Player) -> PathBuf {
+    let side = match player {
+        Player::Left => "left",
+        Player::Right => "right",
+    };
+    PathBuf::from(REPLAY_DIR).join(format!("{side}.json"))
+}
+/// Inputs of the current match, saved for the replay controller when it ends.
+#[derive(Resource, Default)]
+pub struct InputRecorder {
+    time: f32,
+    left: Vec<RecordedInput>,
+    right: Vec<RecordedInput>,
+}
+pub fn record_inputs(
+    mut recorder: ResMut<InputRecorder>,
+    paddles: Query<(&Paddle, &PaddleInput, &Controller)>,
+    time: Res<Time>,
+) {
+    recorder.time += time.delta_seconds();
+    let now = recorder.time;
+    for (paddle, input, controller) in paddles.iter() {
+        if controller.0.kind() == ControllerKind::Replay {
+            continue;
+        }
+        let inputs = match paddle.player {
+            Player::Left => &mut recorder.left,
+            Player::Right => &mut recorder.right,
+        };
+        //Only store changes to keep the files small
+        if inputs.last().map(|last| last.input) != Some(*input) {
+            inputs.push(RecordedInput {
+                time: now,
+                input: *input,
+            });
+        }
+    }
+}
+/// Writes the finished match's inputs for the replay controller. The recorder is only cleared
+/// when the next match starts, so pausing mid-match doesn't lose what came before.
+pub fn save_recordings(recorder: Res<InputRecorder>) {
+    for (player, inputs) in [
+        (Player::Left, &recorder.left),
+        (Player::Right, &recorder.right),
+    ] {
+        if inputs.is_empty() {
+            continue;
+        }
+        let result = fs::create_dir_all(REPLAY_DIR).and_then(|_| {
+            let json = serde_json::to_string(inputs).map_err(std::io::Error::other)?;
+            fs::write(replay_path(player), json)
+        });
+        if let Err(err) = result {
+            eprintln!("Error: failed to save replay: {err}");
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec2;
+
+    const HALF_HEIGHT: f32 = 100.;
+
+    fn crossing(velocity: Vec2, x: f32) -> f32 {
+        predict_crossing(Vec2::ZERO, velocity, x, HALF_HEIGHT)
+    }
+
+    #[test]
+    fn straight_paths_need_no_bounce() {
+        assert_eq!(
+            predict_crossing(vec2(0., 30.), vec2(100., 0.), 50., HALF_HEIGHT),
+            30.
+        );
+        assert_eq!(crossing(vec2(100., 100.), 50.), 50.);
+        assert_eq!(crossing(vec2(-100., 40.), -100.), 40.);
+    }
+
+    #[test]
+    fn bounces_fold_back_into_the_court() {
+        //Off the top wall once
+        assert_eq!(crossing(vec2(100., 100.), 150.), 50.);
+        //Off the top and then the bottom wall
+        assert_eq!(crossing(vec2(100., 100.), 350.), -50.);
+        //Moving left and down
+        assert_eq!(crossing(vec2(-100., -100.), -150.), -50.);
+    }
+
+    #[test]
+    fn level_ball_stays_where_it_is() {
+        assert_eq!(
+            predict_crossing(vec2(0., 20.), vec2(0., 300.), 200., HALF_HEIGHT),
+            20.
+        );
+        assert_eq!(
+            predict_crossing(vec2(0., 20.), vec2(100., 300.), 200., 0.),
+            20.
+        );
+    }
+}
@@ -0,0 +1,64 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+@group(0) @binding(2) var history_texture: texture_2d<f32>;
+struct CrtFilter {
+    intensity: f32,
+}
+@group(0) @binding(3) var<uniform> settings: CrtFilter;
+
+const PI: f32 = 3.14159265;
+const BARREL: f32 = 0.08;
+const SCANLINES: f32 = 0.35;
+const PERSISTENCE: f32 = 0.75;
+const GLOW_THRESHOLD: f32 = 0.6;
+const GLOW_STRENGTH: f32 = 1.6;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    // Written to the other history texture and read back next frame
+    @location(1) history: vec4<f32>,
+}
+
+// Current frame with the fading image of the previous frames on top
+fn phosphor(uv: vec2<f32>) -> vec3<f32> {
+    let current = textureSampleLevel(screen_texture, texture_sampler, uv, 0.0).rgb;
+    let previous = textureSampleLevel(history_texture, texture_sampler, uv, 0.0).rgb;
+    return max(current, previous * PERSISTENCE * settings.intensity);
+}
+
+fn glow(uv: vec2<f32>, texel: vec2<f32>) -> vec3<f32> {
+    var sum = vec3<f32>(0.0);
+    for (var x = -2; x <= 2; x += 1) {
+        for (var y = -2; y <= 2; y += 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel * 2.0;
+            let sample = textureSampleLevel(screen_texture, texture_sampler, uv + offset, 0.0).rgb;
+            sum += max(sample - vec3<f32>(GLOW_THRESHOLD), vec3<f32>(0.0));
+        }
+    }
+    return sum / 25.0 * GLOW_STRENGTH;
+}
+
+fn barrel(uv: vec2<f32>) -> vec2<f32> {
+    let centered = uv * 2.0 - 1.0;
+    let distorted = centered * (1.0 + dot(centered, centered) * BARREL * settings.intensity);
+    return distorted * 0.5 + 0.5;
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> FragmentOutput {
+    let dimensions = vec2<f32>(textureDimensions(screen_texture));
+    let uv = barrel(in.uv);
+    var color = phosphor(uv) + glow(uv, 1.0 / dimensions) * settings.intensity;
+    let scanline = 0.5 + 0.5 * sin(uv.y * dimensions.y * PI);
+    color *= 1.0 - scanline * SCANLINES * settings.intensity;
+    // Black outside the curved screen
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    color = select(vec3<f32>(0.0), color, inside);
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(color, 1.0);
+    out.history = vec4<f32>(phosphor(in.uv), 1.0);
+    return out;
+}
@@ -0,0 +1,348 @@
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_2d::graph::{Core2d, Node2d};
+use bevy::core_pipeline::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
+use bevy::ecs::query::QueryItem;
+use bevy::math::uvec2;
+use bevy::prelude::*;
+use bevy::render::extract_component::{
+    ComponentUniforms, DynamicUniformIndex, ExtractComponentPlugin, UniformComponentPlugin,
+};
+use bevy::render::render_graph::{
+    NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+};
+use bevy::render::render_resource::binding_types::{sampler, texture_2d, uniform_buffer};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::{RenderContext, RenderDevice};
+use bevy::render::texture::BevyDefault;
+use bevy::render::view::ViewTarget;
+use bevy::render::{Render, RenderApp, RenderSet};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::audio_plugin::percent;
+use crate::config::{load_config, save_config};
+use crate::menu_plugin::{add_menu_entry, on_off, MenuAction, MenuEntry};
+
+const CRT_CONFIG: &str = "crt";
+const CRT_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x5c7a_12e4_9b31_4f08_a6d2_83c1_e0f4_7b29);
+const INTENSITY_STEP: f32 = 0.1;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CrtSettings {
+    pub enabled: bool,
+    pub intensity: f32,
+}
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            intensity: 0.6,
+        }
+    }
+}
+/// Cleared when there is no renderer or the pipeline fails to build, which turns the filter off.
+#[derive(Resource, Clone)]
+pub struct CrtSupported(Arc<AtomicBool>);
+impl CrtSupported {
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+//The ShaderType derive emits a private `check` function for the field layout that nothing calls
+#[allow(dead_code)]
+mod uniform {
+    use bevy::prelude::*;
+    use bevy::render::extract_component::ExtractComponent;
+    use bevy::render::render_resource::ShaderType;
+
+    /// Added to the camera while the filter is enabled.
+    #[derive(Component, Default, Clone, Copy, ExtractComponent, ShaderType)]
+    pub struct CrtFilter {
+        pub intensity: f32,
+    }
+}
+use uniform::CrtFilter;
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct CrtLabel;
+pub struct CrtPlugin;
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut App) {
+        let supported = CrtSupported(Arc::new(AtomicBool::new(false)));
+        app.insert_resource(load_config::<CrtSettings>(CRT_CONFIG))
+            .insert_resource(supported.clone())
+            .add_systems(Update, sync_crt_filter);
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "CRT Filter".into(),
+                action: MenuAction::Adjust {
+                    value: |world| {
+                        if world.resource::<CrtSupported>().get() {
+                            on_off(world.resource::<CrtSettings>().enabled)
+                        } else {
+                            "Unsupported".into()
+                        }
+                    },
+                    adjust: |world, _| {
+                        adjust_crt(world, |settings| settings.enabled ^= true);
+                    },
+                },
+                order: 44,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "CRT Intensity".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(world.resource::<CrtSettings>().intensity),
+                    adjust: |world, delta| {
+                        adjust_crt(world, |settings| {
+                            settings.intensity = (settings.intensity
+                                + delta as f32 * INTENSITY_STEP)
+                                .clamp(INTENSITY_STEP, 1.);
+                        });
+                    },
+                },
+                order: 45,
+            },
+        );
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        supported.0.store(true, Ordering::Relaxed);
+        render_app
+            .insert_resource(supported)
+            .init_resource::<CrtHistory>()
+            .add_systems(
+                Render,
+                prepare_crt_history.in_set(RenderSet::PrepareResources),
+            )
+            .add_render_graph_node::<ViewNodeRunner<CrtNode>>(Core2d, CrtLabel)
+            .add_render_graph_edges(
+                Core2d,
+                (
+                    Node2d::Tonemapping,
+                    CrtLabel,
+                    Node2d::EndMainPassPostProcessing,
+                ),
+            );
+        load_internal_asset!(app, CRT_SHADER_HANDLE, "crt.wgsl", Shader::from_wgsl);
+        app.add_plugins((
+            ExtractComponentPlugin::<CrtFilter>::default(),
+            UniformComponentPlugin::<CrtFilter>::default(),
+        ));
+    }
+    fn finish(&self, app: &mut App) {
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<CrtPipeline>();
+    }
+}
+fn adjust_crt(world: &mut World, change: impl FnOnce(&mut CrtSettings)) {
+    let mut settings = world.resource_mut::<CrtSettings>();
+    change(&mut settings);
+    save_config(CRT_CONFIG, settings.as_ref());
+}
+fn sync_crt_filter(
+    mut commands: Commands,
+    settings: Res<CrtSettings>,
+    supported: Res<CrtSupported>,
+    mut cameras: Query<(Entity, Option<&mut CrtFilter>), With<Camera2d>>,
+) {
+    let enabled = settings.enabled && supported.get();
+    for (entity, filter) in cameras.iter_mut() {
+        match (enabled, filter) {
+            (true, Some(mut filter)) => {
+                if filter.intensity != settings.intensity {
+                    filter.intensity = settings.intensity;
+                }
+            }
+            (true, None) => {
+                commands.entity(entity).insert(CrtFilter {
+                    intensity: settings.intensity,
+                });
+            }
+            (false, Some(_)) => {
+                commands.entity(entity).remove::<CrtFilter>();
+            }
+            (false, None) => {}
+        }
+    }
+}
+#[derive(Resource)]
+struct CrtPipeline {
+    layout: BindGroupLayout,
+    sampler: Sampler,
+    pipeline_id: CachedRenderPipelineId,
+}
+impl FromWorld for CrtPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let layout = render_device.create_bind_group_layout(
+            "crt_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    uniform_buffer::<CrtFilter>(true),
+                ),
+            ),
+        );
+        let sampler = render_device.create_sampler(&SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let target = Some(ColorTargetState {
+            format: TextureFormat::bevy_default(),
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        });
+        let pipeline_id =
+            world
+                .resource_mut::<PipelineCache>()
+                .queue_render_pipeline(RenderPipelineDescriptor {
+                    label: Some("crt_pipeline".into()),
+                    layout: vec![layout.clone()],
+                    vertex: fullscreen_shader_vertex_state(),
+                    fragment: Some(FragmentState {
+                        shader: CRT_SHADER_HANDLE,
+                        shader_defs: vec![],
+                        entry_point: "fragment".into(),
+                        targets: vec![target.clone(), target],
+                    }),
+                    primitive: PrimitiveState::default(),
+                    depth_stencil: None,
+                    multisample: MultisampleState::default(),
+                    push_constant_ranges: vec![],
+                });
+        Self {
+            layout,
+            sampler,
+            pipeline_id,
+        }
+    }
+}
+/// Two textures that take turns holding the previous frame for phosphor persistence.
+#[derive(Resource, Default)]
+struct CrtHistory {
+    textures: Vec<TextureView>,
+    size: UVec2,
+    read: usize,
+}
+fn prepare_crt_history(
+    mut history: ResMut<CrtHistory>,
+    views: Query<&ViewTarget, With<CrtFilter>>,
+    render_device: Res<RenderDevice>,
+) {
+    let Some(view) = views.iter().next() else {
+        //Forget the old frames so they don't flash up when the filter is turned back on
+        history.textures.clear();
+        return;
+    };
+    let size = view.main_texture().size();
+    let size = uvec2(size.width, size.height);
+    if history.textures.is_empty() || history.size != size {
+        history.size = size;
+        history.textures = (0..2)
+            .map(|_| {
+                render_device
+                    .create_texture(&TextureDescriptor {
+                        label: Some("crt_history"),
+                        size: Extent3d {
+                            width: size.x,
+                            height: size.y,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: TextureFormat::bevy_default(),
+                        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&TextureViewDescriptor::default())
+            })
+            .collect();
+    }
+    history.read = 1 - history.read;
+}
+#[derive(Default)]
+struct CrtNode;
+impl ViewNode for CrtNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static CrtFilter,
+        &'static DynamicUniformIndex<CrtFilter>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, _filter, uniform_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let crt_pipeline = world.resource::<CrtPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let history = world.resource::<CrtHistory>();
+        let Some(pipeline) = pipeline_cache.get_render_pipeline(crt_pipeline.pipeline_id) else {
+            if let CachedPipelineState::Err(err) =
+                pipeline_cache.get_render_pipeline_state(crt_pipeline.pipeline_id)
+            {
+                let supported = world.resource::<CrtSupported>();
+                if supported.get() {
+                    eprintln!("Error: CRT filter unavailable: {err}");
+                    supported.0.store(false, Ordering::Relaxed);
+                }
+            }
+            return Ok(());
+        };
+        let Some(uniforms) = world.resource::<ComponentUniforms<CrtFilter>>().binding() else {
+            return Ok(());
+        };
+        if history.textures.len() != 2 {
+            return Ok(());
+        }
+        let post_process = view_target.post_process_write();
+        let bind_group = render_context.render_device().create_bind_group(
+            "crt_bind_group",
+            &crt_pipeline.layout,
+            &BindGroupEntries::sequential((
+                post_process.source,
+                &crt_pipeline.sampler,
+                &history.textures[history.read],
+                uniforms,
+            )),
+        );
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("crt_pass"),
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: post_process.destination,
+                    resolve_target: None,
+                    ops: Operations::default(),
+                }),
+                Some(RenderPassColorAttachment {
+                    view: &history.textures[1 - history.read],
+                    resolve_target: None,
+                    ops: Operations::default(),
+                }),
+            ],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[uniform_index.index()]);
+        render_pass.draw(0..3, 0..1);
+        Ok(())
+    }
+}
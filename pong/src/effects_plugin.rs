@@ -0,0 +1,328 @@
+use bevy::ecs::system::SystemParam;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use crate::config::{load_config, save_config};
+use crate::game_events::{GameEventSet, GoalScored, PaddleHit, WallBounce};
+use crate::layout_plugin::CourtRoot;
+use crate::menu_plugin::{add_menu_entry, on_off, MenuAction, MenuEntry};
+use crate::theme_plugin::{ActiveTheme, ThemeRole, Themed};
+use crate::{Ball, Paddle, Player, Settings};
+
+const EFFECTS_CONFIG: &str = "effects";
+const MAX_SHAKE_OFFSET: f32 = 12.;
+const SHAKE_DECAY: f32 = 1.5;
+const FLASH_COLOR: Color = Color::rgb(1., 0.9, 0.3);
+const FLASH_SECONDS: f32 = 0.12;
+const TRAIL_INTERVAL: f32 = 0.015;
+const TRAIL_SECONDS: f32 = 0.2;
+const PARTICLE_SECONDS: f32 = 0.5;
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EffectsSettings {
+    pub screen_shake: bool,
+    pub paddle_flash: bool,
+    pub ball_trail: bool,
+    pub particles: bool,
+}
+impl Default for EffectsSettings {
+    fn default() -> Self {
+        Self {
+            screen_shake: true,
+            paddle_flash: true,
+            ball_trail: true,
+            particles: true,
+        }
+    }
+}
+#[derive(Resource, Default)]
+struct ScreenShake {
+    trauma: f32,
+}
+#[derive(Component)]
+struct PaddleFlash(Timer);
+#[derive(Component)]
+struct Fading {
+    timer: Timer,
+    color: Color,
+}
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+}
+#[derive(Resource)]
+struct TrailTimer(Timer);
+/// Triggers the visual effects that are enabled in [`EffectsSettings`].
+#[derive(SystemParam)]
+pub struct Effects<'w, 's> {
+    commands: Commands<'w, 's>,
+    settings: Res<'w, EffectsSettings>,
+    shake: ResMut<'w, ScreenShake>,
+    paddles: Query<'w, 's, (Entity, &'static Paddle)>,
+    theme: Res<'w, ActiveTheme>,
+    court: Res<'w, CourtRoot>,
+}
+impl<'w, 's> Effects<'w, 's> {
+    /// Adds camera shake proportional to `speed`, the ball speed at the moment of impact.
+    pub fn shake(&mut self, speed: f32) {
+        if self.settings.screen_shake {
+            self.shake.trauma = (self.shake.trauma + speed / 2000.).min(1.);
+        }
+    }
+    pub fn flash_paddle(&mut self, player: &Player) {
+        if !self.settings.paddle_flash {
+            return;
+        }
+        for (entity, paddle) in self.paddles.iter() {
+            if paddle.player == *player {
+                self.commands
+                    .entity(entity)
+                    .insert(PaddleFlash(Timer::from_seconds(
+                        FLASH_SECONDS,
+                        TimerMode::Once,
+                    )));
+            }
+        }
+    }
+    pub fn burst(&mut self, position: Vec2, count: u32) {
+        if !self.settings.particles {
+            return;
+        }
+        let color = self.theme.0.ball;
+        let mut thread_rng = rand::thread_rng();
+        for _ in 0..count {
+            let angle = thread_rng.gen_range(0. ..TAU);
+            let speed = thread_rng.gen_range(60. ..260.);
+            let size = thread_rng.gen_range(2. ..5.);
+            let entity =
+                spawn_effect_square(&mut self.commands, self.court.0, position, size, color);
+            self.commands.entity(entity).insert((
+                Particle {
+                    velocity: vec2(angle.cos(), angle.sin()) * speed,
+                },
+                Fading {
+                    timer: Timer::from_seconds(PARTICLE_SECONDS, TimerMode::Once),
+                    color,
+                },
+            ));
+        }
+    }
+}
+fn react_to_play(
+    mut effects: Effects,
+    mut hits: EventReader<PaddleHit>,
+    mut bounces: EventReader<WallBounce>,
+    mut goals: EventReader<GoalScored>,
+) {
+    for hit in hits.read() {
+        effects.shake(hit.speed);
+        effects.flash_paddle(&hit.player);
+    }
+    for bounce in bounces.read() {
+        effects.burst(bounce.position, 8);
+    }
+    for goal in goals.read() {
+        effects.shake(goal.speed * 2.);
+        effects.burst(goal.position, 40);
+    }
+}
+fn spawn_effect_square(
+    commands: &mut Commands,
+    court: Entity,
+    position: Vec2,
+    size: f32,
+    color: Color,
+) -> Entity {
+    commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shapes::Rectangle {
+                    extents: vec2(size, size),
+                    origin: RectangleOrigin::Center,
+                }),
+                spatial: SpatialBundle {
+                    transform: Transform::from_translation(position.extend(0.5)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Fill::color(color),
+        ))
+        .set_parent(court)
+        .id()
+}
+pub struct EffectsPlugin;
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<EffectsSettings>(EFFECTS_CONFIG))
+            .init_resource::<ScreenShake>()
+            .insert_resource(TrailTimer(Timer::from_seconds(
+                TRAIL_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .add_systems(
+                Update,
+                (
+                    react_to_play.after(GameEventSet),
+                    shake_camera,
+                    flash_paddles,
+                    spawn_trail,
+                    move_particles,
+                    fade_effects,
+                ),
+            );
+        add_effect_toggle(
+            app,
+            "Screen Shake",
+            40,
+            |world| on_off(world.resource::<EffectsSettings>().screen_shake),
+            |world, _| adjust_effects(world, |settings| settings.screen_shake ^= true),
+        );
+        add_effect_toggle(
+            app,
+            "Paddle Flash",
+            41,
+            |world| on_off(world.resource::<EffectsSettings>().paddle_flash),
+            |world, _| adjust_effects(world, |settings| settings.paddle_flash ^= true),
+        );
+        add_effect_toggle(
+            app,
+            "Ball Trail",
+            42,
+            |world| on_off(world.resource::<EffectsSettings>().ball_trail),
+            |world, _| adjust_effects(world, |settings| settings.ball_trail ^= true),
+        );
+        add_effect_toggle(
+            app,
+            "Particles",
+            43,
+            |world| on_off(world.resource::<EffectsSettings>().particles),
+            |world, _| adjust_effects(world, |settings| settings.particles ^= true),
+        );
+    }
+}
+fn add_effect_toggle(
+    app: &mut App,
+    label: &str,
+    order: i32,
+    value: fn(&World) -> String,
+    adjust: fn(&mut World, i32),
+) {
+    add_menu_entry(
+        app,
+        MenuEntry {
+            label: label.into(),
+            action: MenuAction::Adjust { value, adjust },
+            order,
+        },
+    );
+}
+fn adjust_effects(world: &mut World, change: impl FnOnce(&mut EffectsSettings)) {
+    let mut settings = world.resource_mut::<EffectsSettings>();
+    change(&mut settings);
+    save_config(EFFECTS_CONFIG, settings.as_ref());
+}
+fn shake_camera(
+    mut shake: ResMut<ScreenShake>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+    settings: Res<EffectsSettings>,
+    time: Res<Time>,
+) {
+    let Ok(mut camera) = camera.get_single_mut() else {
+        return;
+    };
+    if !settings.screen_shake {
+        shake.trauma = 0.;
+    }
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.);
+    if shake.trauma <= 0. {
+        camera.translation.x = 0.;
+        camera.translation.y = 0.;
+        return;
+    }
+    let mut thread_rng = rand::thread_rng();
+    let strength = shake.trauma.powi(2) * MAX_SHAKE_OFFSET;
+    camera.translation.x = thread_rng.gen_range(-1. ..1.) * strength;
+    camera.translation.y = thread_rng.gen_range(-1. ..1.) * strength;
+}
+fn flash_paddles(
+    mut commands: Commands,
+    mut paddles: Query<(Entity, &mut PaddleFlash, &mut Fill, &Themed)>,
+    theme: Res<ActiveTheme>,
+    time: Res<Time>,
+) {
+    for (entity, mut flash, mut fill, themed) in paddles.iter_mut() {
+        flash.0.tick(time.delta());
+        let color = theme.0.color(themed.0);
+        if flash.0.finished() {
+            fill.color = color;
+            commands.entity(entity).remove::<PaddleFlash>();
+        } else {
+            let t = flash.0.fraction();
+            fill.color = lerp_color(FLASH_COLOR, color, t);
+        }
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn spawn_trail(
+    mut commands: Commands,
+    mut timer: ResMut<TrailTimer>,
+    ball: Query<&Transform, With<Ball>>,
+    settings: Res<EffectsSettings>,
+    game_settings: Res<Settings>,
+    theme: Res<ActiveTheme>,
+    court: Res<CourtRoot>,
+    time: Res<Time>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() || !settings.ball_trail {
+        return;
+    }
+    let Ok(ball) = ball.get_single() else {
+        return;
+    };
+    let color = theme.0.color(ThemeRole::Ball).with_a(0.5);
+    let entity = spawn_effect_square(
+        &mut commands,
+        court.0,
+        ball.translation.truncate(),
+        game_settings.ball_size,
+        color,
+    );
+    commands.entity(entity).insert(Fading {
+        timer: Timer::from_seconds(TRAIL_SECONDS, TimerMode::Once),
+        color,
+    });
+}
+fn move_particles(mut particles: Query<(&mut Transform, &Particle)>, time: Res<Time>) {
+    for (mut transform, particle) in particles.iter_mut() {
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+    }
+}
+fn fade_effects(
+    mut commands: Commands,
+    mut faders: Query<(Entity, &mut Fading, &mut Fill)>,
+    time: Res<Time>,
+) {
+    for (entity, mut fading, mut fill) in faders.iter_mut() {
+        fading.timer.tick(time.delta());
+        if fading.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = fading.color.a() * (1. - fading.timer.fraction());
+        fill.color = fading.color.with_a(alpha);
+    }
+}
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    Color::rgba(
+        from.r() + (to.r() - from.r()) * t,
+        from.g() + (to.g() - from.g()) * t,
+        from.b() + (to.b() - from.b()) * t,
+        from.a() + (to.a() - from.a()) * t,
+    )
+}
@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+
+use crate::Player;
+
+/// A new ball was served.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BallSpawned {
+    pub velocity: Vec2,
+}
+/// A paddle returned the ball.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PaddleHit {
+    pub player: Player,
+    pub position: Vec2,
+    /// Ball speed after the return.
+    pub speed: f32,
+    /// Where on the paddle the ball struck, -1 at the bottom to 1 at the top.
+    pub offset: f32,
+}
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WallBounce {
+    pub position: Vec2,
+    /// Ball speed after the bounce.
+    pub speed: f32,
+}
+/// The ball left the court. The ball is already gone when this is read.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GoalScored {
+    pub scorer: Player,
+    /// Where the ball left the court.
+    pub position: Vec2,
+    pub speed: f32,
+}
+/// A side reached the points needed to win.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MatchOver {
+    pub winner: Player,
+}
+/// Systems that send game events. Subscribers that must see an event in the frame it was sent,
+/// such as the stats read when the summary opens, run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameEventSet;
+/// Registers the events sent while a match is played, so features can react to play without
+/// adding checks to the ball systems.
+pub struct GameEventsPlugin;
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BallSpawned>()
+            .add_event::<PaddleHit>()
+            .add_event::<WallBounce>()
+            .add_event::<GoalScored>()
+            .add_event::<MatchOver>();
+    }
+}
@@ -0,0 +1,345 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use crate::paddle_control::{FollowTuning, PaddleInput};
+use crate::simulation::Simulation;
+use crate::{Player, Settings, PLAY_AREA};
+
+const DEFAULT_DT: f32 = 1. / 60.;
+const DEFAULT_POINTS: u32 = 5;
+const DEFAULT_STACK: usize = 4;
+/// Pixels per court unit of pixel observations, giving an 80x44 image.
+const PIXEL_SCALE: f32 = 0.1;
+/// Episodes neither side has won by then are cut short.
+const MAX_EPISODE_SECONDS: f32 = 600.;
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+enum Request {
+    Reset {
+        seed: Option<u64>,
+    },
+    /// Directions for the left and right paddle, -1 to 1, held for `repeat` steps.
+    Step {
+        left: f32,
+        right: f32,
+        repeat: Option<u32>,
+    },
+}
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Observation {
+    Features(Vec<f32>),
+    Pixels(Vec<u8>),
+}
+#[derive(Serialize, Default)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    observation: Option<Observation>,
+    /// Dimensions of the observation, sent on reset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shape: Option<Vec<usize>>,
+    /// Points won minus points lost by the left paddle this step, the right paddle gets the
+    /// negation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reward: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    done: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+struct Options {
+    dt: f32,
+    points: u32,
+    /// Number of stacked frames when observing pixels.
+    pixels: Option<usize>,
+}
+/// The state of an episode between requests.
+struct Environment {
+    options: Options,
+    simulation: Simulation,
+    frames: VecDeque<Vec<u8>>,
+    done: bool,
+}
+impl Environment {
+    fn reset(&mut self, seed: u64) -> Response {
+        self.simulation = Simulation::new(Settings::default(), FollowTuning::default(), seed);
+        self.done = false;
+        self.frames.clear();
+        if let Some(stack) = self.options.pixels {
+            let frame = render(&self.simulation);
+            self.frames.extend(std::iter::repeat_n(frame, stack));
+        }
+        Response {
+            observation: Some(self.observe()),
+            shape: Some(match self.options.pixels {
+                Some(stack) => {
+                    let size = pixel_size();
+                    vec![stack, size.y as usize, size.x as usize]
+                }
+                None => vec![FEATURES],
+            }),
+            ..Default::default()
+        }
+    }
+    fn step(&mut self, left: f32, right: f32, repeat: u32) -> Response {
+        if self.done {
+            return Response {
+                error: Some("episode is over, reset first".into()),
+                ..Default::default()
+            };
+        }
+        let inputs = [PaddleInput::direction(left), PaddleInput::direction(right)];
+        let mut reward = 0.;
+        for _ in 0..repeat.max(1) {
+            reward += match self
+                .simulation
+                .step(inputs, self.options.dt)
+                .and_then(|step| step.scorer)
+            {
+                Some(Player::Left) => 1.,
+                Some(Player::Right) => -1.,
+                None => 0.,
+            };
+            if self.options.pixels.is_some() {
+                self.frames.pop_front();
+                self.frames.push_back(render(&self.simulation));
+            }
+            self.done = self
+                .simulation
+                .scores
+                .iter()
+                .any(|score| *score >= self.options.points)
+                || self.simulation.time >= MAX_EPISODE_SECONDS;
+            if self.done {
+                break;
+            }
+        }
+        Response {
+            observation: Some(self.observe()),
+            reward: Some(reward),
+            done: Some(self.done),
+            ..Default::default()
+        }
+    }
+    fn observe(&self) -> Observation {
+        match self.options.pixels {
+            Some(_) => Observation::Pixels(self.frames.iter().flatten().copied().collect()),
+            None => Observation::Features(features(&self.simulation).to_vec()),
+        }
+    }
+}
+const FEATURES: usize = 9;
+/// Ball position, ball velocity, paddle positions and paddle speeds, scaled to about -1..1, then
+/// 1 while the ball is in play and 0 while waiting for a serve.
+fn features(simulation: &Simulation) -> [f32; FEATURES] {
+    let half = PLAY_AREA / 2.;
+    let max_speed = simulation.settings.max_paddle_speed;
+    let (position, velocity, in_play) = match simulation.ball {
+        Some(ball) => (ball.position / half, ball.velocity / max_speed, 1.),
+        None => (Vec2::ZERO, Vec2::ZERO, 0.),
+    };
+    let [left, right] = simulation.paddles;
+    [
+        position.x,
+        position.y,
+        velocity.x,
+        velocity.y,
+        left.y / half.y,
+        right.y / half.y,
+        left.speed / max_speed,
+        right.speed / max_speed,
+        in_play,
+    ]
+}
+fn pixel_size() -> UVec2 {
+    (PLAY_AREA * PIXEL_SCALE).ceil().as_uvec2()
+}
+/// Draws the paddles and ball white on black, one byte per pixel, rows from the top.
+fn render(simulation: &Simulation) -> Vec<u8> {
+    let size = pixel_size();
+    let mut pixels = vec![0; (size.x * size.y) as usize];
+    let settings = &simulation.settings;
+    let mut fill = |centre: Vec2, extents: Vec2| {
+        let to_pixels = |position: Vec2| {
+            Vec2::new(position.x + PLAY_AREA.x / 2., PLAY_AREA.y / 2. - position.y) * PIXEL_SCALE
+        };
+        let min = to_pixels(centre + Vec2::new(-extents.x, extents.y) / 2.).floor();
+        let max = to_pixels(centre + Vec2::new(extents.x, -extents.y) / 2.).ceil();
+        let min = min.clamp(Vec2::ZERO, size.as_vec2()).as_uvec2();
+        let max = max.clamp(Vec2::ZERO, size.as_vec2()).as_uvec2();
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                pixels[(y * size.x + x) as usize] = 255;
+            }
+        }
+    };
+    for ((paddle, x), player) in simulation
+        .paddles
+        .iter()
+        .zip([-settings.paddle_x, settings.paddle_x])
+        .zip([Player::Left, Player::Right])
+    {
+        fill(Vec2::new(x, paddle.y), settings.player_paddle_size(player));
+    }
+    if let Some(ball) = simulation.ball {
+        fill(ball.position, Vec2::splat(settings.ball_size));
+    }
+    pixels
+}
+/// Serves a training environment over stdin and stdout, one JSON object per line. Send
+/// `{"cmd":"reset","seed":1}` then `{"cmd":"step","left":0.5,"right":-1}` until `done`.
+/// Takes `[--dt SECONDS] [--points N] [--pixels [STACK]]`.
+pub fn run(args: impl Iterator<Item = String>) {
+    let mut options = Options {
+        dt: DEFAULT_DT,
+        points: DEFAULT_POINTS,
+        pixels: None,
+    };
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--dt" => args.next().and_then(|dt| dt.parse().ok()).map(|dt| {
+                options.dt = dt;
+            }),
+            "--points" => args
+                .next()
+                .and_then(|points| points.parse().ok())
+                .map(|points| {
+                    options.points = points;
+                }),
+            "--pixels" => {
+                let stack = args.next_if(|stack| !stack.starts_with("--"));
+                stack
+                    .map_or(Some(DEFAULT_STACK), |stack| stack.parse().ok())
+                    .filter(|stack| *stack > 0)
+                    .map(|stack| {
+                        options.pixels = Some(stack);
+                    })
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Error: bad argument {arg}");
+            return;
+        }
+    }
+    let mut environment = Environment {
+        options,
+        simulation: Simulation::new(Settings::default(), FollowTuning::default(), 0),
+        frames: VecDeque::new(),
+        done: true,
+    };
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Reset { seed }) => {
+                environment.reset(seed.unwrap_or_else(|| rand::thread_rng().gen()))
+            }
+            Ok(Request::Step {
+                left,
+                right,
+                repeat,
+            }) => environment.step(left, right, repeat.unwrap_or(1)),
+            Err(err) => Response {
+                error: Some(err.to_string()),
+                ..Default::default()
+            },
+        };
+        let written = serde_json::to_writer(&mut stdout, &response)
+            .map_err(io::Error::other)
+            .and_then(|_| writeln!(stdout))
+            .and_then(|_| stdout.flush());
+        if written.is_err() {
+            break;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn environment(points: u32, pixels: Option<usize>) -> Environment {
+        Environment {
+            options: Options {
+                dt: DEFAULT_DT,
+                points,
+                pixels,
+            },
+            simulation: Simulation::new(Settings::default(), FollowTuning::default(), 0),
+            frames: VecDeque::new(),
+            done: true,
+        }
+    }
+
+    #[test]
+    fn requests_parse() {
+        let reset = serde_json::from_str(r#"{"cmd":"reset","seed":1}"#);
+        assert!(matches!(reset, Ok(Request::Reset { seed: Some(1) })));
+        let step = serde_json::from_str(r#"{"cmd":"step","left":0.5,"right":-1,"repeat":4}"#);
+        assert!(matches!(
+            step,
+            Ok(Request::Step {
+                left: 0.5,
+                right: -1.,
+                repeat: Some(4),
+            })
+        ));
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"jump"}"#).is_err());
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"step","left":1}"#).is_err());
+    }
+
+    #[test]
+    fn same_seed_resets_to_the_same_episode() {
+        let play = || {
+            let mut environment = environment(DEFAULT_POINTS, None);
+            let mut responses = vec![serde_json::to_string(&environment.reset(7)).unwrap()];
+            for _ in 0..20 {
+                let response = environment.step(0.5, -0.5, 30);
+                responses.push(serde_json::to_string(&response).unwrap());
+            }
+            responses
+        };
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn goal_ends_a_one_point_episode() {
+        let mut environment = environment(1, None);
+        environment.reset(3);
+        let mut last = Response::default();
+        for _ in 0..100 {
+            last = environment.step(0., 0., 60);
+            if last.done == Some(true) {
+                break;
+            }
+        }
+        assert_eq!(last.done, Some(true));
+        let reward = last.reward.unwrap();
+        assert!(reward == 1. || reward == -1.);
+        assert!(environment.step(0., 0., 1).error.is_some());
+    }
+
+    #[test]
+    fn pixel_observations_stack_whole_frames() {
+        let mut environment = environment(DEFAULT_POINTS, Some(3));
+        let response = environment.reset(1);
+        assert_eq!(response.shape, Some(vec![3, 44, 80]));
+        for response in [response, environment.step(1., -1., 10)] {
+            let Some(Observation::Pixels(pixels)) = response.observation else {
+                panic!("expected a pixel observation");
+            };
+            assert_eq!(pixels.len(), 3 * 44 * 80);
+            assert!(pixels.contains(&255));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::audio_plugin::percent;
+use crate::config::{load_config, save_config};
+use crate::match_stats::{MatchStats, Sides};
+use crate::menu_plugin::{add_menu_entry, MenuAction, MenuEntry};
+use crate::{GameData, Paddle, Player, Score, Settings};
+
+const HANDICAP_CONFIG: &str = "handicaps";
+const SCALE_STEP: f32 = 0.1;
+
+/// Adjustments to one side, so players of different skill can have a close match.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Handicap {
+    /// Multiplies the paddle height.
+    pub paddle_scale: f32,
+    /// Multiplies the paddle's top speed and acceleration.
+    pub speed_scale: f32,
+    /// Points this side starts each match with.
+    pub starting_points: u32,
+    /// Multiplies the ball speed while the ball is on this side of the court.
+    pub ball_speed: f32,
+}
+impl Default for Handicap {
+    fn default() -> Self {
+        Self {
+            paddle_scale: 1.,
+            speed_scale: 1.,
+            starting_points: 0,
+            ball_speed: 1.,
+        }
+    }
+}
+impl Handicap {
+    /// A short summary for the match summary, as in "SIZE 120% SPEED 100% +2 BALL 90%".
+    pub fn describe(&self) -> String {
+        format!(
+            "SIZE {} SPEED {} +{} BALL {}",
+            percent(self.paddle_scale),
+            percent(self.speed_scale),
+            self.starting_points,
+            percent(self.ball_speed),
+        )
+    }
+}
+/// The side the handicap entries in the menu change.
+#[derive(Resource)]
+struct HandicapSide(Player);
+/// Lets each side have its own paddle size, paddle speed, head start and ball speed, set from
+/// the menu and kept between runs.
+pub struct HandicapPlugin;
+impl Plugin for HandicapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HandicapSide(Player::Left))
+            .add_systems(PreStartup, load_handicaps)
+            .add_systems(Update, resize_paddles.run_if(resource_changed::<Settings>));
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Handicap Side".into(),
+                action: MenuAction::Adjust {
+                    value: |world| {
+                        match world.resource::<HandicapSide>().0 {
+                            Player::Left => "Left",
+                            Player::Right => "Right",
+                        }
+                        .into()
+                    },
+                    adjust: |world, _| {
+                        let mut side = world.resource_mut::<HandicapSide>();
+                        side.0 = match side.0 {
+                            Player::Left => Player::Right,
+                            Player::Right => Player::Left,
+                        };
+                    },
+                },
+                order: 13,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Paddle Size".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(handicap(world).paddle_scale),
+                    adjust: |world, delta| {
+                        adjust_handicap(world, |handicap| {
+                            handicap.paddle_scale = step_scale(handicap.paddle_scale, delta, 2.)
+                        })
+                    },
+                },
+                order: 14,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Paddle Speed".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(handicap(world).speed_scale),
+                    adjust: |world, delta| {
+                        adjust_handicap(world, |handicap| {
+                            handicap.speed_scale = step_scale(handicap.speed_scale, delta, 2.)
+                        })
+                    },
+                },
+                order: 15,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Head Start".into(),
+                action: MenuAction::Adjust {
+                    value: |world| handicap(world).starting_points.to_string(),
+                    adjust: |world, delta| {
+                        let max = world.resource::<Settings>().points_to_win - 1;
+                        adjust_handicap(world, |handicap| {
+                            handicap.starting_points = handicap
+                                .starting_points
+                                .saturating_add_signed(delta)
+                                .min(max)
+                        })
+                    },
+                },
+                order: 16,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Ball Speed".into(),
+                action: MenuAction::Adjust {
+                    value: |world| percent(handicap(world).ball_speed),
+                    adjust: |world, delta| {
+                        adjust_handicap(world, |handicap| {
+                            handicap.ball_speed = step_scale(handicap.ball_speed, delta, 1.5)
+                        })
+                    },
+                },
+                order: 17,
+            },
+        );
+    }
+}
+fn handicap(world: &World) -> Handicap {
+    let side = world.resource::<HandicapSide>().0;
+    *world.resource::<Settings>().handicaps.get(side)
+}
+fn step_scale(scale: f32, delta: i32, max: f32) -> f32 {
+    //Round so repeated steps don't drift away from the tenths
+    ((scale + delta as f32 * SCALE_STEP).clamp(0.5, max) / SCALE_STEP).round() * SCALE_STEP
+}
+fn adjust_handicap(world: &mut World, change: impl FnOnce(&mut Handicap)) {
+    let side = world.resource::<HandicapSide>().0;
+    let mut settings = world.resource_mut::<Settings>();
+    change(settings.handicaps.get_mut(side));
+    save_config(HANDICAP_CONFIG, &settings.handicaps);
+    let handicaps = settings.handicaps;
+    //A match that hasn't started yet picks up the new head start straight away
+    world.resource_scope(|world, mut stats: Mut<MatchStats>| {
+        if stats.time_played > 0. {
+            return;
+        }
+        start_scores(handicaps, &mut world.resource_mut::<GameData>(), &mut stats);
+        //update_scores redraws the digits once these are gone
+        let scores: Vec<_> = world
+            .query_filtered::<Entity, With<Score>>()
+            .iter(world)
+            .collect();
+        for score in scores {
+            world.entity_mut(score).despawn_recursive();
+        }
+    });
+}
+fn load_handicaps(
+    mut settings: ResMut<Settings>,
+    mut game_data: ResMut<GameData>,
+    mut stats: ResMut<MatchStats>,
+) {
+    settings.handicaps = load_config(HANDICAP_CONFIG);
+    start_scores(settings.handicaps, &mut game_data, &mut stats);
+}
+/// Sets the scores a match starts from and records the handicaps it is played with.
+pub fn start_scores(handicaps: Sides<Handicap>, game_data: &mut GameData, stats: &mut MatchStats) {
+    game_data.left_score = handicaps.left.starting_points;
+    game_data.right_score = handicaps.right.starting_points;
+    game_data.last_ball_speed = None;
+    stats.score = Sides {
+        left: handicaps.left.starting_points,
+        right: handicaps.right.starting_points,
+    };
+    stats.handicaps = handicaps;
+}
+fn resize_paddles(settings: Res<Settings>, mut paddles: Query<(&mut Path, &Paddle)>) {
+    for (mut path, paddle) in paddles.iter_mut() {
+        *path = GeometryBuilder::build_as(&shapes::Rectangle {
+            extents: settings.player_paddle_size(paddle.player),
+            origin: RectangleOrigin::Center,
+        });
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::math::{uvec2, vec2};
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use common_modules::debug_text_plugin::{change_debug_text, DebugText};
+use std::f32::consts::FRAC_PI_2;
+
+use crate::{Player, PLAY_AREA};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+/// How the court fits the window. In portrait the court is turned a quarter turn clockwise so
+/// the left player is at the top and the right player at the bottom.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    pub orientation: Orientation,
+    /// World units per logical pixel.
+    pub scale: f32,
+}
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            orientation: Orientation::Landscape,
+            scale: 1.,
+        }
+    }
+}
+impl Layout {
+    /// Size of the visible area in world units.
+    pub fn view_size(&self) -> Vec2 {
+        match self.orientation {
+            Orientation::Landscape => PLAY_AREA,
+            Orientation::Portrait => vec2(PLAY_AREA.y, PLAY_AREA.x),
+        }
+    }
+    pub fn court_transform(&self) -> Transform {
+        match self.orientation {
+            Orientation::Landscape => Transform::IDENTITY,
+            Orientation::Portrait => Transform::from_rotation(Quat::from_rotation_z(-FRAC_PI_2)),
+        }
+    }
+    pub fn world_to_court(&self, position: Vec2) -> Vec2 {
+        match self.orientation {
+            Orientation::Landscape => position,
+            Orientation::Portrait => vec2(-position.y, position.x),
+        }
+    }
+    pub fn score_anchor(&self, player: Player) -> Vec2 {
+        match (self.orientation, player) {
+            (Orientation::Landscape, Player::Left) => vec2(-200., 150.),
+            (Orientation::Landscape, Player::Right) => vec2(200., 150.),
+            (Orientation::Portrait, Player::Left) => vec2(-100., 200.),
+            (Orientation::Portrait, Player::Right) => vec2(-100., -200.),
+        }
+    }
+}
+/// Parent of everything that lives on the court. Its children use court coordinates, with the
+/// paddles on the x axis, whatever the orientation.
+#[derive(Resource)]
+pub struct CourtRoot(pub Entity);
+impl FromWorld for CourtRoot {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.spawn((SpatialBundle::default(), Court)).id())
+    }
+}
+#[derive(Component)]
+struct Court;
+/// Parent of a player's score digits, placed by the layout.
+#[derive(Component)]
+pub struct ScoreAnchor(pub Player);
+pub struct LayoutPlugin;
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Layout>()
+            .init_resource::<CourtRoot>()
+            .add_systems(Update, (update_layout, apply_layout).chain());
+    }
+}
+fn update_layout(
+    mut layout: ResMut<Layout>,
+    mut camera: Query<(&mut Camera, &mut OrthographicProjection)>,
+    window: Query<&Window>,
+    mut debug_text: ResMut<DebugText>,
+) {
+    let (Ok(window), Ok((mut camera, mut projection))) =
+        (window.get_single(), camera.get_single_mut())
+    else {
+        return;
+    };
+    let window_size = uvec2(window.physical_width(), window.physical_height());
+    if window_size.x == 0 || window_size.y == 0 {
+        return;
+    }
+    let mut new_layout = Layout {
+        orientation: if window_size.x < window_size.y {
+            Orientation::Portrait
+        } else {
+            Orientation::Landscape
+        },
+        scale: 1.,
+    };
+    //Fit the court inside the window and letterbox the rest
+    let view_size = new_layout.view_size();
+    let fit = (window_size.x as f32 / view_size.x).min(window_size.y as f32 / view_size.y);
+    let physical_size = (view_size * fit)
+        .as_uvec2()
+        .min(window_size)
+        .max(UVec2::ONE);
+    new_layout.scale = window.scale_factor() / fit;
+    let viewport = Viewport {
+        physical_position: (window_size - physical_size) / 2,
+        physical_size,
+        ..Default::default()
+    };
+    if camera
+        .viewport
+        .as_ref()
+        .map(|old| (old.physical_position, old.physical_size))
+        != Some((viewport.physical_position, viewport.physical_size))
+    {
+        camera.viewport = Some(viewport);
+    }
+    if projection.scale != new_layout.scale {
+        projection.scale = new_layout.scale;
+    }
+    if *layout != new_layout {
+        *layout = new_layout;
+    }
+    change_debug_text(
+        &mut debug_text,
+        "Camera Scale",
+        &format!("{}", projection.scale),
+    );
+    change_debug_text(
+        &mut debug_text,
+        "Window Dimensions",
+        &format!("({}, {})", window.width(), window.height()),
+    );
+    change_debug_text(&mut debug_text, "Play Area", &PLAY_AREA.to_string());
+}
+fn apply_layout(
+    layout: Res<Layout>,
+    mut court: Query<&mut Transform, With<Court>>,
+    mut anchors: Query<(&mut Transform, Ref<ScoreAnchor>), Without<Court>>,
+) {
+    if layout.is_changed() {
+        for mut transform in court.iter_mut() {
+            *transform = layout.court_transform();
+        }
+    }
+    for (mut transform, anchor) in anchors.iter_mut() {
+        if !layout.is_changed() && !anchor.is_added() {
+            continue;
+        }
+        let position = layout.score_anchor(anchor.0);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
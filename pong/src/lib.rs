@@ -0,0 +1,780 @@
+use achievements::AchievementsPlugin;
+use audio_plugin::PongAudioPlugin;
+use bevy::app::{App, Startup, Update};
+use bevy::asset::io::AssetSourceId;
+use bevy::math::{vec2, vec3};
+use bevy::prelude::*;
+use bevy::tasks::block_on;
+use bevy::window::PrimaryWindow;
+use bevy_prototype_lyon::prelude::*;
+use bracket::BracketPlugin;
+use common_modules::bitmap_font_plugin::BitmapFontPlugin;
+use common_modules::debug_text_plugin::{change_debug_text, DebugKeys, DebugText};
+use crt_plugin::CrtPlugin;
+use effects_plugin::EffectsPlugin;
+use game_events::{
+    BallSpawned, GameEventSet, GameEventsPlugin, GoalScored, MatchOver, PaddleHit, WallBounce,
+};
+use handicap::{Handicap, HandicapPlugin};
+use layout_plugin::{CourtRoot, Layout, LayoutPlugin, ScoreAnchor};
+use match_stats::{MatchStats, MatchStatsPlugin, Sides};
+use menu_plugin::{AppState, MenuPlugin};
+use music_plugin::MusicPlugin;
+use mutators::{MutatorSet, MutatorsPlugin};
+use paddle_control::{PaddleControlPlugin, PaddleControlSet, PaddleInput, PlayerControls};
+use practice::PracticePlugin;
+use profiles::ProfilesPlugin;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use save_game::SaveGamePlugin;
+use serde::{Deserialize, Serialize};
+use simulation::{serve, step_ball, step_paddle, PaddleState};
+use speed_curve::{SpeedCurve, SpeedCurvePlugin};
+use std::path::Path;
+use std::time::Duration;
+use theme_plugin::{ThemePlugin, ThemeRole, Themed};
+
+mod achievements;
+mod audio_plugin;
+mod bracket;
+mod config;
+mod controllers;
+mod crt_plugin;
+mod effects_plugin;
+mod game_events;
+mod gym;
+mod handicap;
+mod layout_plugin;
+mod match_stats;
+mod menu_plugin;
+mod music_plugin;
+mod mutators;
+mod paddle_control;
+mod practice;
+mod profiles;
+mod save_game;
+mod script_bot;
+mod simulation;
+mod sound_pack;
+mod speed_curve;
+pub mod stats;
+mod synth;
+mod theme_plugin;
+mod tournament;
+
+const IS_FULLSCREEN: bool = false;
+const PLAY_AREA: Vec2 = vec2(800., 436.);
+
+/// Court position of the cursor, `None` while it is outside the window.
+#[derive(Resource, Debug)]
+struct MouseCoords(Option<Vec2>);
+/// Court positions of the fingers currently on the screen.
+#[derive(Resource, Debug, Default)]
+struct TouchCoords(Vec<Vec2>);
+#[derive(Resource)]
+/// Counts down to the next serve while there is no ball.
+struct BallTimer(Timer);
+#[derive(Component)]
+struct Ball {
+    velocity: Vec2,
+}
+#[derive(Component, Debug)]
+pub struct Score {
+    player: Player,
+}
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Player {
+    Left,
+    Right,
+}
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(skip)]
+    fullscreen: bool,
+    max_paddle_speed: f32,
+    paddle_acceleration: f32,
+    friction: f32,
+    paddle_size: Vec2,
+    paddle_x: f32,
+    ball_size: f32,
+    max_spawn_speed: Vec2,
+    min_spawn_speed: Vec2,
+    score_spacing: f32,
+    speed_multiplier: f32,
+    points_to_win: u32,
+    #[serde(default)]
+    handicaps: Sides<Handicap>,
+    #[serde(default)]
+    speed_curve: SpeedCurve,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fullscreen: IS_FULLSCREEN,
+            max_paddle_speed: 500.,
+            paddle_acceleration: 3000.,
+            friction: 700.,
+            paddle_x: 350.,
+            paddle_size: vec2(10., 50.),
+            ball_size: 10.,
+            max_spawn_speed: vec2(300., 300.),
+            min_spawn_speed: vec2(200., 0.),
+            score_spacing: 20.,
+            speed_multiplier: 1.1,
+            points_to_win: 11,
+            handicaps: Sides::default(),
+            speed_curve: SpeedCurve::default(),
+        }
+    }
+}
+impl Settings {
+    fn player_paddle_size(&self, player: Player) -> Vec2 {
+        let scale = self.handicaps.get(player).paddle_scale;
+        vec2(self.paddle_size.x, self.paddle_size.y * scale)
+    }
+    fn player_max_paddle_speed(&self, player: Player) -> f32 {
+        self.max_paddle_speed * self.handicaps.get(player).speed_scale
+    }
+    fn player_paddle_acceleration(&self, player: Player) -> f32 {
+        self.paddle_acceleration * self.handicaps.get(player).speed_scale
+    }
+}
+#[derive(Resource)]
+struct DigitSpriteSheet(Handle<TextureAtlasLayout>);
+/// Seeds the serves. Kept as a seed that moves on with every serve rather than a generator, so a
+/// saved match serves the same balls once it is resumed.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Copy)]
+struct ServeSeed(u64);
+impl Default for ServeSeed {
+    fn default() -> Self {
+        Self(rand::thread_rng().gen())
+    }
+}
+impl ServeSeed {
+    fn next_rng(&mut self) -> StdRng {
+        let mut rng = StdRng::seed_from_u64(self.0);
+        self.0 = rng.gen();
+        rng
+    }
+}
+#[derive(Resource, Debug)]
+struct GameData {
+    left_y: f32,
+    right_y: f32,
+    left_score: u32,
+    right_score: u32,
+    /// Speed the last point ended at, for serving at it when speed carries over.
+    last_ball_speed: Option<f32>,
+}
+#[derive(Component, Debug)]
+struct Paddle {
+    speed: f32,
+    player: Player,
+}
+impl Default for GameData {
+    fn default() -> Self {
+        Self {
+            left_y: 0.,
+            right_y: 0.,
+            left_score: 0,
+            right_score: 0,
+            last_ball_speed: None,
+        }
+    }
+}
+impl FromWorld for DigitSpriteSheet {
+    fn from_world(world: &mut World) -> Self {
+        let texture_atlas = TextureAtlasLayout::from_grid(
+            vec2(3., 5.),
+            5,
+            2,
+            Some(vec2(1., 1.)),
+            Some(vec2(1., 1.)),
+        );
+        let mut texture_atlases = world
+            .get_resource_mut::<Assets<TextureAtlasLayout>>()
+            .unwrap();
+        let texture_atlas_handle = texture_atlases.add(texture_atlas);
+        Self(texture_atlas_handle)
+    }
+}
+pub struct PongPlugin;
+impl Plugin for PongPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ShapePlugin,
+            LayoutPlugin,
+            PaddleControlPlugin,
+            BitmapFontPlugin,
+            MenuPlugin,
+            PongAudioPlugin,
+            MusicPlugin,
+            EffectsPlugin,
+            ThemePlugin,
+            CrtPlugin,
+            MatchStatsPlugin,
+        ))
+        .add_plugins((
+            GameEventsPlugin,
+            ProfilesPlugin,
+            AchievementsPlugin,
+            SaveGamePlugin,
+            BracketPlugin,
+            HandicapPlugin,
+            SpeedCurvePlugin,
+            MutatorsPlugin,
+            PracticePlugin,
+        ))
+        .init_resource::<DigitSpriteSheet>()
+        .insert_resource(DebugText {
+            color: Color::WHITE,
+            ..Default::default()
+        })
+        .insert_resource(MouseCoords(None))
+        .init_resource::<TouchCoords>()
+        .insert_resource(BallTimer(Timer::new(
+            Duration::from_millis(1000),
+            TimerMode::Repeating,
+        )))
+        .insert_resource(DebugKeys(vec![
+            "Camera Scale".into(),
+            "Window Dimensions".into(),
+            "Play Area".into(),
+            "Mouse Coords".into(),
+            "Directions".into(),
+            "Left Bot".into(),
+            "Right Bot".into(),
+            "Sounds Played".into(),
+            "Volume".into(),
+        ]))
+        .insert_resource(Settings::default())
+        .insert_resource(GameData::default())
+        .init_resource::<ServeSeed>()
+        .insert_resource(ClearColor(Color::BLACK))
+        .add_systems(Startup, (setup, spawn_background))
+        .add_systems(
+            Update,
+            (
+                handle_actions,
+                accelerate_paddles
+                    .after(PaddleControlSet)
+                    .after(MutatorSet)
+                    .run_if(in_state(AppState::Playing).or_else(in_state(AppState::Practice))),
+                (
+                    (update_ball, score_goals).chain().in_set(GameEventSet),
+                    spawn_ball.in_set(GameEventSet),
+                )
+                    .after(accelerate_paddles)
+                    .run_if(in_state(AppState::Playing)),
+                update_scores.after(GameEventSet),
+                get_cursor_coords,
+                get_touch_coords,
+            ),
+        );
+    }
+}
+pub fn run() {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("--tournament") => return tournament::run(args),
+        Some("--gym") => return gym::run(args),
+        _ => {}
+    }
+    App::new()
+        .add_plugins((
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        present_mode: bevy::window::PresentMode::AutoNoVsync,
+                        mode: if IS_FULLSCREEN {
+                            bevy::window::WindowMode::BorderlessFullscreen
+                        } else {
+                            bevy::window::WindowMode::Windowed
+                        },
+                        title: "Pong".into(),
+                        resolution: PLAY_AREA.into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .set(ImagePlugin::default_nearest()),
+            common_modules::debug_text_plugin::DebugTextPlugin,
+            PongPlugin,
+        ))
+        .run();
+}
+fn setup(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    layout: Res<Layout>,
+    court: Res<CourtRoot>,
+) {
+    let camera = Camera::default();
+    commands.spawn(Camera2dBundle {
+        camera,
+        ..Default::default()
+    });
+    for player in [Player::Left, Player::Right] {
+        //update_scores fills in the digits, starting from any head start
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_translation(
+                layout.score_anchor(player).extend(-1.),
+            )),
+            ScoreAnchor(player),
+        ));
+        spawn_paddle(player, &mut commands, settings.as_ref(), court.0);
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn spawn_ball(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut ball_timer: ResMut<BallTimer>,
+    time: Res<Time>,
+    court: Res<CourtRoot>,
+    balls: Query<(), With<Ball>>,
+    mut serve_seed: ResMut<ServeSeed>,
+    game_data: Res<GameData>,
+    mut spawned: EventWriter<BallSpawned>,
+) {
+    if !balls.is_empty() || !ball_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let (y, velocity) = serve(
+        &mut serve_seed.next_rng(),
+        &settings,
+        game_data.last_ball_speed,
+    );
+    insert_ball(&mut commands, &settings, vec2(0., y), velocity, court.0);
+    spawned.send(BallSpawned { velocity });
+}
+fn insert_ball(
+    commands: &mut Commands,
+    settings: &Settings,
+    position: Vec2,
+    velocity: Vec2,
+    court: Entity,
+) {
+    let square = spawn_square(
+        vec2(settings.ball_size, settings.ball_size),
+        position.y,
+        commands,
+        court,
+    );
+    commands.entity(square).insert((
+        Transform::from_translation(position.extend(0.)),
+        Themed(ThemeRole::Ball),
+        Ball { velocity },
+    ));
+}
+fn spawn_background(mut commands: Commands, court: Res<CourtRoot>) {
+    let line_amount = 30;
+    let section_height = PLAY_AREA.y / line_amount as f32;
+    let line_height = section_height * 5. / 7.;
+    let line_width = line_height / 7.;
+    let mut current_position = PLAY_AREA.y / 2. - line_height / 2.;
+    for _ in 0..line_amount {
+        let line = spawn_square(
+            vec2(line_width, line_height),
+            current_position,
+            &mut commands,
+            court.0,
+        );
+        commands.entity(line).insert(Themed(ThemeRole::CourtLines));
+        current_position -= section_height;
+    }
+    commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shapes::Rectangle {
+                    extents: PLAY_AREA,
+                    origin: RectangleOrigin::Center,
+                }),
+                spatial: SpatialBundle {
+                    transform: Transform::from_translation(vec3(0., 0., -2.)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Fill::color(Color::BLACK),
+            Themed(ThemeRole::Background),
+        ))
+        .set_parent(court.0);
+}
+fn spawn_square(size: Vec2, position: f32, commands: &mut Commands, court: Entity) -> Entity {
+    commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shapes::Rectangle {
+                    extents: size,
+                    origin: RectangleOrigin::Center,
+                }),
+                spatial: SpatialBundle {
+                    transform: Transform::from_translation(vec3(0., position, 0.)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Fill::color(Color::WHITE),
+        ))
+        .set_parent(court)
+        .id()
+}
+
+fn num_length(num: u32) -> u32 {
+    num.checked_ilog10().unwrap_or(0) + 1
+}
+fn get_digits(n: usize) -> Vec<usize> {
+    fn x_inner(n: usize, xs: &mut Vec<usize>) {
+        if n >= 10 {
+            x_inner(n / 10, xs);
+        }
+        xs.push(n % 10);
+    }
+    let mut xs = Vec::new();
+    x_inner(n, &mut xs);
+    xs
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_ball(
+    mut ball: Query<(&mut Ball, &mut Transform, Entity)>,
+    paddles: Query<(&Transform, &Paddle), Without<Ball>>,
+    time: Res<Time>,
+    mut commands: Commands,
+    settings: Res<Settings>,
+    game_data: Res<GameData>,
+    mut hits: EventWriter<PaddleHit>,
+    mut bounces: EventWriter<WallBounce>,
+    mut goals: EventWriter<GoalScored>,
+) {
+    let mut ball = match ball.get_single_mut() {
+        Ok(ball) => ball,
+        Err(_) => return,
+    };
+    let mut paddle_scales = [1.; 2];
+    for (transform, paddle) in paddles.iter() {
+        paddle_scales[paddle.player as usize] = transform.scale.y;
+    }
+    let step = step_ball(
+        ball.1.translation.truncate(),
+        ball.0.velocity,
+        [game_data.left_y, game_data.right_y],
+        paddle_scales,
+        [game_data.left_score, game_data.right_score],
+        &settings,
+        time.delta_seconds(),
+    );
+    if let Some(hit) = step.hit {
+        hits.send(PaddleHit {
+            player: hit.player,
+            position: hit.position,
+            speed: hit.speed,
+            offset: hit.impact_offset,
+        });
+    }
+    if let Some(scorer) = step.scorer {
+        commands.get_entity(ball.2).unwrap().despawn_recursive();
+        goals.send(GoalScored {
+            scorer,
+            position: step.position,
+            speed: step.velocity.length(),
+        });
+        return;
+    }
+    if step.bounced {
+        bounces.send(WallBounce {
+            position: step.position,
+            speed: step.velocity.length(),
+        });
+    }
+    ball.0.velocity = step.velocity;
+    ball.1.translation.x = step.position.x;
+    ball.1.translation.y = step.position.y;
+}
+fn score_goals(
+    mut goals: EventReader<GoalScored>,
+    mut game_data: ResMut<GameData>,
+    settings: Res<Settings>,
+    mut match_over: EventWriter<MatchOver>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for goal in goals.read() {
+        game_data.last_ball_speed = Some(goal.speed);
+        match goal.scorer {
+            Player::Left => game_data.left_score += 1,
+            Player::Right => game_data.right_score += 1,
+        }
+        if game_data.left_score.max(game_data.right_score) >= settings.points_to_win {
+            match_over.send(MatchOver {
+                winner: goal.scorer,
+            });
+            next_state.set(AppState::Summary);
+        }
+    }
+}
+fn spawn_paddle(player: Player, commands: &mut Commands, settings: &Settings, court: Entity) {
+    commands
+        .spawn((
+            (
+                ShapeBundle {
+                    path: GeometryBuilder::build_as(&shapes::Rectangle {
+                        extents: settings.player_paddle_size(player),
+                        origin: RectangleOrigin::Center,
+                    }),
+                    spatial: SpatialBundle {
+                        transform: match player {
+                            Player::Left => {
+                                Transform::from_translation(vec3(-settings.paddle_x, 0., 0.))
+                            }
+                            Player::Right => {
+                                Transform::from_translation(vec3(settings.paddle_x, 0., 0.))
+                            }
+                        },
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                Fill::color(Color::WHITE),
+            ),
+            Paddle { speed: 0., player },
+            PaddleInput::default(),
+            Themed(ThemeRole::Paddle(player)),
+        ))
+        .set_parent(court);
+}
+/// Redraws the score digits after a goal, or for a side with none, as at startup or once a reset
+/// has removed them.
+#[allow(clippy::too_many_arguments)]
+fn update_scores(
+    mut goals: EventReader<GoalScored>,
+    game_data: Res<GameData>,
+    mut scores: Query<(&mut Score, &mut TextureAtlas, &mut Transform)>,
+    anchors: Query<(Entity, &ScoreAnchor)>,
+    mut commands: Commands,
+    sprite_atlas: Res<DigitSpriteSheet>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+) {
+    let scored = goals.read().count() > 0;
+    let mut digit_sprites: [Vec<_>; 2] = [vec![], vec![]];
+    for score in scores.iter_mut() {
+        digit_sprites[score.0.player as usize].push(score);
+    }
+    if !scored && digit_sprites.iter().all(|sprites| !sprites.is_empty()) {
+        return;
+    }
+    let anchor = |player: Player| {
+        anchors
+            .iter()
+            .find(|(_, anchor)| anchor.0 == player)
+            .map(|(entity, _)| entity)
+    };
+    for player in [Player::Left, Player::Right] {
+        let Some(anchor) = anchor(player) else {
+            continue;
+        };
+        let score = match player {
+            Player::Left => game_data.left_score,
+            Player::Right => game_data.right_score,
+        };
+        let sprites = &mut digit_sprites[player as usize];
+        let score_len = num_length(score);
+        let digits = get_digits(score as usize);
+        if sprites.is_empty() {
+            //At startup, after a reset or a restored save, lay the digits out as if they were added
+            //one by one
+            for (i, digit) in digits.iter().enumerate() {
+                spawn_score(
+                    player,
+                    &mut commands,
+                    &asset_server,
+                    &sprite_atlas,
+                    (2 * i) as f32 + 1. - digits.len() as f32,
+                    &settings,
+                    *digit,
+                    anchor,
+                );
+            }
+            continue;
+        }
+        if score_len > sprites.len() as u32 {
+            spawn_score(
+                player,
+                &mut commands,
+                &asset_server,
+                &sprite_atlas,
+                (score_len - 1) as f32,
+                &settings,
+                digits[digits.len() - 1],
+                anchor,
+            );
+            for score in sprites.iter_mut() {
+                score.2.translation.x -= settings.score_spacing;
+            }
+        }
+        for (score, digit) in sprites.iter_mut().zip(digits.iter()) {
+            score.1.index = *digit;
+        }
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn spawn_score(
+    player: Player,
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    sprite_atlas: &DigitSpriteSheet,
+    //Slot along the score, in multiples of the score spacing
+    position: f32,
+    settings: &Settings,
+    index: usize,
+    anchor: Entity,
+) -> Entity {
+    let sprite = asset_server.load("spritesheets/digits.png");
+    commands
+        .spawn((
+            SpriteSheetBundle {
+                atlas: TextureAtlas {
+                    layout: sprite_atlas.0.clone(),
+                    index,
+                },
+                texture: sprite,
+                sprite: Sprite {
+                    color: Color::WHITE,
+                    custom_size: Some(vec2(27., 45.)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(vec3(
+                    position * settings.score_spacing,
+                    0.,
+                    0.,
+                )),
+                ..Default::default()
+            },
+            Score { player },
+            Themed(ThemeRole::Score),
+        ))
+        .set_parent(anchor)
+        .id()
+}
+fn accelerate_paddles(
+    mut query: Query<(&mut Transform, &mut Paddle, &PaddleInput)>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut game_data: ResMut<GameData>,
+    controls: Res<PlayerControls>,
+    mut debug_text: ResMut<DebugText>,
+    mut stats: ResMut<MatchStats>,
+) {
+    let mut directions = [0.; 2];
+    for mut paddle in query.iter_mut() {
+        let start = paddle.0.translation.y;
+        let (moved, direction) = step_paddle(
+            PaddleState {
+                y: start,
+                speed: paddle.1.speed,
+            },
+            paddle.1.player,
+            paddle.0.scale.y,
+            *paddle.2,
+            &controls.follow,
+            &settings,
+            time.delta_seconds(),
+        );
+        paddle.0.translation.y = moved.y;
+        paddle.1.speed = moved.speed;
+        directions[paddle.1.player as usize] = direction;
+        *stats.paddle_distance.get_mut(paddle.1.player) += (paddle.0.translation.y - start).abs();
+        match paddle.1.player {
+            Player::Left => game_data.left_y = paddle.0.translation.y,
+            Player::Right => game_data.right_y = paddle.0.translation.y,
+        };
+    }
+    change_debug_text(
+        &mut debug_text,
+        "Directions",
+        &format!("({:.2}, {:.2})", directions[0], directions[1]),
+    );
+}
+fn handle_actions(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    window: Query<&mut Window>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) && *state.get() == AppState::Playing {
+        next_state.set(AppState::Menu);
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyF) || keyboard_input.just_pressed(KeyCode::F11) {
+        settings.fullscreen = !settings.fullscreen;
+        fullscreen(window, settings.fullscreen);
+    }
+}
+fn fullscreen(mut window: Query<&mut Window>, fullscreen: bool) {
+    match window.get_single_mut() {
+        Ok(mut window) => {
+            if fullscreen {
+                window.mode = bevy::window::WindowMode::BorderlessFullscreen
+            } else {
+                window.mode = bevy::window::WindowMode::Windowed
+            }
+        }
+        Err(err) => eprintln!("Failed to get window: {err:#?}"),
+    };
+}
+/// Whether `path` is a folder in Bevy's asset source, wherever that was set up to read from.
+/// Lets optional folders be skipped without `load_folder` logging an error for them.
+fn asset_folder_exists(asset_server: &AssetServer, path: &str) -> bool {
+    let Ok(source) = asset_server.get_source(AssetSourceId::Default) else {
+        return false;
+    };
+    block_on(source.reader().is_directory(Path::new(path))).unwrap_or(false)
+}
+/// Converts a window position to one relative to the camera's viewport, `None` when it falls in
+/// the letterbox bars. `viewport_to_world` expects positions relative to the viewport.
+fn window_to_viewport(camera: &Camera, position: Vec2) -> Option<Vec2> {
+    match camera.logical_viewport_rect() {
+        Some(viewport) if viewport.contains(position) => Some(position - viewport.min),
+        Some(_) => None,
+        None => Some(position),
+    }
+}
+fn get_cursor_coords(
+    mut mycoords: ResMut<MouseCoords>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut debug_text: ResMut<DebugText>,
+    layout: Res<Layout>,
+) {
+    let (camera, camera_transform) = camera.single();
+    let window = window.single();
+    if let Some(world_position) = window
+        .cursor_position()
+        .and_then(|cursor| window_to_viewport(camera, cursor))
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        .map(|ray| ray.origin.truncate())
+    {
+        let position = layout.world_to_court(world_position);
+        mycoords.0 = Some(position);
+        change_debug_text(&mut debug_text, "Mouse Coords", &format!("{position:.2?}"));
+    } else {
+        mycoords.0 = None;
+    }
+}
+fn get_touch_coords(
+    mut touch_coords: ResMut<TouchCoords>,
+    touches: Res<Touches>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    layout: Res<Layout>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    touch_coords.0 = touches
+        .iter()
+        .filter_map(|touch| window_to_viewport(camera, touch.position()))
+        .filter_map(|position| camera.viewport_to_world_2d(camera_transform, position))
+        .map(|position| layout.world_to_court(position))
+        .collect();
+}
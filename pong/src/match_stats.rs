@@ -0,0 +1,239 @@
+use bevy::math::vec3;
+use bevy::prelude::*;
+use common_modules::bitmap_font_plugin::{BitmapText, BitmapTextBundle};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::controllers::InputRecorder;
+use crate::game_events::{BallSpawned, GameEventSet, GoalScored, PaddleHit, WallBounce};
+use crate::handicap::{start_scores, Handicap};
+use crate::layout_plugin::Layout;
+use crate::menu_plugin::{despawn_screen, spawn_screen, AppState};
+use crate::{Ball, BallTimer, GameData, Paddle, Player, Score, Settings};
+
+const STATS_DIR: &str = "./match_stats";
+
+/// One value for each player.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sides<T> {
+    pub left: T,
+    pub right: T,
+}
+impl<T> Sides<T> {
+    pub fn get(&self, player: Player) -> &T {
+        match player {
+            Player::Left => &self.left,
+            Player::Right => &self.right,
+        }
+    }
+    pub fn get_mut(&mut self, player: Player) -> &mut T {
+        match player {
+            Player::Left => &mut self.left,
+            Player::Right => &mut self.right,
+        }
+    }
+}
+/// Everything counted during the current match. Reset when the next match starts.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MatchStats {
+    pub score: Sides<u32>,
+    /// Paddle hits in each point, in order.
+    pub rallies: Vec<u32>,
+    pub longest_rally: u32,
+    pub hits: Sides<u32>,
+    pub fastest_ball: f32,
+    /// Points won before either paddle touched the ball.
+    pub points_on_serve: Sides<u32>,
+    /// Seconds spent playing, not counting time in menus.
+    pub time_played: f32,
+    pub paddle_distance: Sides<f32>,
+    #[serde(default)]
+    pub handicaps: Sides<Handicap>,
+    /// Whether each side has trailed 0-5 this match, for the Comeback achievement.
+    #[serde(default)]
+    pub trailed: Sides<bool>,
+    /// Paddle hits so far in the point being played. Not part of the exported stats, the save
+    /// game keeps it on its own.
+    #[serde(skip)]
+    pub rally: u32,
+}
+impl MatchStats {
+    fn record_speed(&mut self, speed: f32) {
+        self.fastest_ball = self.fastest_ball.max(speed);
+    }
+    fn record_hit(&mut self, hit: &PaddleHit) {
+        self.record_speed(hit.speed);
+        *self.hits.get_mut(hit.player) += 1;
+        self.rally += 1;
+    }
+    fn record_goal(&mut self, goal: &GoalScored) {
+        *self.score.get_mut(goal.scorer) += 1;
+        if self.rally == 0 {
+            *self.points_on_serve.get_mut(goal.scorer) += 1;
+        }
+        self.rallies.push(self.rally);
+        self.longest_rally = self.longest_rally.max(self.rally);
+        self.rally = 0;
+    }
+    pub fn average_rally(&self) -> f32 {
+        self.rallies.iter().sum::<u32>() as f32 / self.rallies.len().max(1) as f32
+    }
+    pub fn winner(&self) -> Option<Player> {
+        match self.score.left.cmp(&self.score.right) {
+            std::cmp::Ordering::Greater => Some(Player::Left),
+            std::cmp::Ordering::Less => Some(Player::Right),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+    /// Writes the stats to a new file in the stats folder, returning its path.
+    fn export(&self) -> std::io::Result<PathBuf> {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = PathBuf::from(STATS_DIR).join(format!("match_{seconds}.json"));
+        fs::create_dir_all(STATS_DIR)?;
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+#[derive(Component)]
+struct SummaryScreen;
+pub struct MatchStatsPlugin;
+impl Plugin for MatchStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MatchStats>()
+            .add_systems(Update, count_time.run_if(in_state(AppState::Playing)))
+            .add_systems(Update, record_play.after(GameEventSet))
+            .add_systems(OnEnter(AppState::Summary), spawn_summary)
+            .add_systems(Update, summary_input.run_if(in_state(AppState::Summary)))
+            .add_systems(
+                OnExit(AppState::Summary),
+                (despawn_screen::<SummaryScreen>, reset_match),
+            );
+    }
+}
+fn count_time(mut stats: ResMut<MatchStats>, time: Res<Time>) {
+    stats.time_played += time.delta_seconds();
+}
+pub fn record_play(
+    mut stats: ResMut<MatchStats>,
+    mut spawned: EventReader<BallSpawned>,
+    mut hits: EventReader<PaddleHit>,
+    mut bounces: EventReader<WallBounce>,
+    mut goals: EventReader<GoalScored>,
+) {
+    for ball in spawned.read() {
+        stats.record_speed(ball.velocity.length());
+    }
+    for hit in hits.read() {
+        stats.record_hit(hit);
+    }
+    for bounce in bounces.read() {
+        stats.record_speed(bounce.speed);
+    }
+    for goal in goals.read() {
+        stats.record_goal(goal);
+    }
+}
+fn summary_text(stats: &MatchStats, saved: &str) -> String {
+    let time = stats.time_played as u32;
+    let mut handicaps = String::new();
+    for (side, handicap) in [
+        ("LEFT", stats.handicaps.left),
+        ("RIGHT", stats.handicaps.right),
+    ] {
+        if handicap != Handicap::default() {
+            handicaps += &format!("{side} HANDICAP: {}\n", handicap.describe());
+        }
+    }
+    format!(
+        "SCORE: {} - {}\n\
+        TIME PLAYED: {}:{:02}\n\
+        LONGEST RALLY: {}\n\
+        AVERAGE RALLY: {:.1}\n\
+        FASTEST BALL: {:.0}\n\
+        HITS: {} - {}\n\
+        POINTS ON SERVE: {} - {}\n\
+        PADDLE DISTANCE: {:.0} - {:.0}\n\
+        {handicaps}\n\
+        {saved}\n\
+        ENTER: REMATCH  ESC: MENU",
+        stats.score.left,
+        stats.score.right,
+        time / 60,
+        time % 60,
+        stats.longest_rally,
+        stats.average_rally(),
+        stats.fastest_ball,
+        stats.hits.left,
+        stats.hits.right,
+        stats.points_on_serve.left,
+        stats.points_on_serve.right,
+        stats.paddle_distance.left,
+        stats.paddle_distance.right,
+    )
+}
+fn spawn_summary(mut commands: Commands, stats: Res<MatchStats>, layout: Res<Layout>) {
+    let saved = match stats.export() {
+        Ok(path) => format!("SAVED TO {}", path.display()),
+        Err(err) => {
+            eprintln!("Error: failed to save match stats: {err}");
+            "COULD NOT SAVE STATS".into()
+        }
+    };
+    let title = match stats.winner() {
+        Some(Player::Left) => "LEFT WINS",
+        Some(Player::Right) => "RIGHT WINS",
+        None => "DRAW",
+    };
+    let screen = spawn_screen(&mut commands, layout.view_size(), SummaryScreen, title, 8.);
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(BitmapTextBundle::new(
+            BitmapText::new(summary_text(&stats, &saved), 3.),
+            vec3(0., -20., 1.),
+        ));
+    });
+}
+pub fn summary_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Enter) || keyboard_input.just_pressed(KeyCode::Space) {
+        next_state.set(AppState::Playing);
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+    }
+}
+/// Clears the stats, recorded inputs and court and puts the scores back to the head starts so
+/// the next match starts fresh, when leaving the summary or before a bracket match.
+#[allow(clippy::too_many_arguments)]
+pub fn reset_match(
+    mut commands: Commands,
+    mut stats: ResMut<MatchStats>,
+    mut game_data: ResMut<GameData>,
+    scores: Query<Entity, With<Score>>,
+    balls: Query<Entity, With<Ball>>,
+    mut ball_timer: ResMut<BallTimer>,
+    mut paddles: Query<(&mut Transform, &mut Paddle)>,
+    settings: Res<Settings>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    *stats = MatchStats::default();
+    *recorder = InputRecorder::default();
+    start_scores(settings.handicaps, &mut game_data, &mut stats);
+    //Drop the digits, update_scores redraws each side's starting score once they are gone
+    for score in scores.iter() {
+        commands.entity(score).despawn_recursive();
+    }
+    for ball in balls.iter() {
+        commands.entity(ball).despawn_recursive();
+    }
+    ball_timer.0.reset();
+    for (mut transform, mut paddle) in paddles.iter_mut() {
+        transform.translation.y = 0.;
+        paddle.speed = 0.;
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::app::AppExit;
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use common_modules::bitmap_font_plugin::{BitmapText, BitmapTextBundle};
+
+use crate::layout_plugin::Layout;
+use crate::theme_plugin::{ActiveTheme, ThemeRole, Themed};
+
+const ENTRY_SPACING: f32 = 22.;
+const VISIBLE_ENTRIES: usize = 12;
+
+#[derive(States, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    Menu,
+    Playing,
+    /// The end of match screen.
+    Summary,
+    /// Typing the name of a new player profile.
+    NameEntry,
+    Leaderboard,
+    /// Entering players for a new tournament bracket.
+    BracketSetup,
+    /// The bracket between tournament matches.
+    Bracket,
+    /// Solo breakout practice against a wall of bricks.
+    Practice,
+}
+pub enum MenuAction {
+    Activate(fn(&mut World)),
+    Adjust {
+        value: fn(&World) -> String,
+        adjust: fn(&mut World, i32),
+    },
+}
+pub struct MenuEntry {
+    pub label: String,
+    pub action: MenuAction,
+    /// Entries are listed from the lowest order to the highest.
+    pub order: i32,
+}
+#[derive(Resource, Default)]
+pub struct MenuEntries {
+    entries: Vec<MenuEntry>,
+    selected: usize,
+    scroll: usize,
+}
+/// Registers an entry on the main menu. Can be called from any plugin's `build`.
+pub fn add_menu_entry(app: &mut App, entry: MenuEntry) {
+    let mut entries = app.world.get_resource_or_insert_with(MenuEntries::default);
+    entries.entries.push(entry);
+    entries.entries.sort_by_key(|entry| entry.order);
+}
+/// The value shown for an entry that switches something on and off.
+pub fn on_off(value: bool) -> String {
+    if value { "On" } else { "Off" }.to_string()
+}
+#[derive(Component)]
+struct MenuScreen;
+#[derive(Component)]
+struct MenuLine(usize);
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>()
+            .init_resource::<MenuEntries>()
+            .add_systems(OnEnter(AppState::Menu), spawn_menu)
+            .add_systems(OnExit(AppState::Menu), despawn_screen::<MenuScreen>)
+            .add_systems(
+                Update,
+                (despawn_screen::<MenuScreen>, spawn_menu)
+                    .chain()
+                    .run_if(in_state(AppState::Menu).and_then(resource_changed::<Layout>)),
+            )
+            .add_systems(
+                Update,
+                (menu_input, update_menu_lines)
+                    .chain()
+                    .run_if(in_state(AppState::Menu)),
+            );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Play".into(),
+                action: MenuAction::Activate(|world| {
+                    world
+                        .resource_mut::<NextState<AppState>>()
+                        .set(AppState::Playing)
+                }),
+                order: 0,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Quit".into(),
+                action: MenuAction::Activate(|world| {
+                    world.send_event(AppExit);
+                }),
+                order: 1000,
+            },
+        );
+    }
+}
+/// Spawns a dimmed full screen overlay tagged with `marker`, with `title` across the top.
+/// Returns the overlay so callers can add their own text to it.
+pub fn spawn_screen(
+    commands: &mut Commands,
+    view_size: Vec2,
+    marker: impl Component,
+    title: &str,
+    title_scale: f32,
+) -> Entity {
+    commands
+        .spawn((
+            ShapeBundle {
+                path: GeometryBuilder::build_as(&shapes::Rectangle {
+                    extents: view_size,
+                    origin: RectangleOrigin::Center,
+                }),
+                spatial: SpatialBundle {
+                    transform: Transform::from_translation(vec3(0., 0., 5.)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Fill::color(Color::rgba(0., 0., 0., 0.75)),
+            marker,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                BitmapTextBundle::new(
+                    BitmapText::new(title, title_scale),
+                    vec3(0., view_size.y / 2. - 5. * title_scale, 1.),
+                ),
+                Themed(ThemeRole::Accent),
+            ));
+        })
+        .id()
+}
+/// Removes every screen tagged with `M`, for use with `OnExit`.
+pub fn despawn_screen<M: Component>(mut commands: Commands, screens: Query<Entity, With<M>>) {
+    for screen in screens.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+fn spawn_menu(mut commands: Commands, entries: Res<MenuEntries>, layout: Res<Layout>) {
+    let view_size = layout.view_size();
+    let screen = spawn_screen(&mut commands, view_size, MenuScreen, "PONG", 12.);
+    commands.entity(screen).with_children(|parent| {
+        let top = view_size.y / 2. - 120.;
+        for i in 0..entries.entries.len().min(VISIBLE_ENTRIES) {
+            parent.spawn((
+                BitmapTextBundle::new(
+                    BitmapText::new("", 3.),
+                    vec3(0., top - i as f32 * ENTRY_SPACING, 1.),
+                ),
+                MenuLine(i),
+            ));
+        }
+    });
+}
+fn menu_input(world: &mut World) {
+    let keyboard_input = world.resource::<ButtonInput<KeyCode>>();
+    let up =
+        keyboard_input.just_pressed(KeyCode::ArrowUp) || keyboard_input.just_pressed(KeyCode::KeyW);
+    let down = keyboard_input.just_pressed(KeyCode::ArrowDown)
+        || keyboard_input.just_pressed(KeyCode::KeyS);
+    let left = keyboard_input.just_pressed(KeyCode::ArrowLeft)
+        || keyboard_input.just_pressed(KeyCode::KeyA);
+    let right = keyboard_input.just_pressed(KeyCode::ArrowRight)
+        || keyboard_input.just_pressed(KeyCode::KeyD);
+    let confirm =
+        keyboard_input.just_pressed(KeyCode::Enter) || keyboard_input.just_pressed(KeyCode::Space);
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        world.send_event(AppExit);
+        return;
+    }
+    let mut entries = world.resource_mut::<MenuEntries>();
+    let len = entries.entries.len();
+    if len == 0 {
+        return;
+    }
+    if up {
+        entries.selected = (entries.selected + len - 1) % len;
+    }
+    if down {
+        entries.selected = (entries.selected + 1) % len;
+    }
+    //Keep the selected entry inside the visible window
+    if entries.selected < entries.scroll {
+        entries.scroll = entries.selected;
+    } else if entries.selected >= entries.scroll + VISIBLE_ENTRIES {
+        entries.scroll = entries.selected + 1 - VISIBLE_ENTRIES;
+    }
+    let delta = if left {
+        -1
+    } else if right || confirm {
+        1
+    } else {
+        return;
+    };
+    match entries.entries[entries.selected].action {
+        MenuAction::Activate(activate) => {
+            if confirm {
+                activate(world);
+            }
+        }
+        MenuAction::Adjust { adjust, .. } => adjust(world, delta),
+    }
+}
+fn update_menu_lines(world: &mut World) {
+    let selected_color = world.resource::<ActiveTheme>().0.accent;
+    let lines: Vec<(usize, String, bool)> = {
+        let entries = world.resource::<MenuEntries>();
+        entries
+            .entries
+            .iter()
+            .enumerate()
+            .skip(entries.scroll)
+            .take(VISIBLE_ENTRIES)
+            .map(|(i, entry)| {
+                let text = match &entry.action {
+                    MenuAction::Activate(_) => entry.label.clone(),
+                    MenuAction::Adjust { value, .. } => {
+                        format!("{}: {}", entry.label, value(world))
+                    }
+                };
+                (i - entries.scroll, text, i == entries.selected)
+            })
+            .collect()
+    };
+    let mut query = world.query::<(&MenuLine, &mut BitmapText)>();
+    for (line, mut text) in query.iter_mut(world) {
+        let Some((_, value, selected)) = lines.iter().find(|(i, _, _)| *i == line.0) else {
+            continue;
+        };
+        let value = if *selected {
+            format!("> {value} <")
+        } else {
+            value.clone()
+        };
+        let color = if *selected {
+            selected_color
+        } else {
+            Color::WHITE
+        };
+        if text.text != value || text.color != color {
+            text.text = value;
+            text.color = color;
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use bevy::asset::LoadedFolder;
+use bevy::audio::{PlaybackMode, Source, Volume};
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::audio_plugin::AudioSettings;
+use crate::config::{load_config, save_config};
+use crate::menu_plugin::{add_menu_entry, on_off, AppState, MenuAction, MenuEntry};
+use crate::{asset_folder_exists, Ball, Settings};
+
+const MUSIC_CONFIG: &str = "music";
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MusicSettings {
+    pub enabled: bool,
+    pub crossfade_seconds: f32,
+    /// Speeds the game track up as the ball gets faster.
+    pub tempo_follows_rally: bool,
+    pub max_tempo: f32,
+    /// How much the tempo rises for each doubling of the ball's spawn speed.
+    pub tempo_per_speed: f32,
+    /// Ball speed at which the intense playlist takes over. Disabled when `None`.
+    pub intense_speed: Option<f32>,
+}
+impl Default for MusicSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            crossfade_seconds: 2.,
+            tempo_follows_rally: true,
+            max_tempo: 1.3,
+            tempo_per_speed: 0.15,
+            intense_speed: Some(900.),
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Playlist {
+    Menu,
+    Game,
+    Intense,
+}
+impl Playlist {
+    fn folder(&self) -> &'static str {
+        match self {
+            Playlist::Menu => "music/menu",
+            Playlist::Game => "music/game",
+            Playlist::Intense => "music/intense",
+        }
+    }
+}
+#[derive(Resource, Default)]
+struct MusicPlayer {
+    folders: HashMap<Playlist, Handle<LoadedFolder>>,
+    playlist: Option<Playlist>,
+    track_index: usize,
+    durations: HashMap<AssetId<AudioSource>, Duration>,
+    measuring: HashMap<AssetId<AudioSource>, Task<Duration>>,
+}
+impl MusicPlayer {
+    fn tracks(
+        &self,
+        playlist: Playlist,
+        folders: &Assets<LoadedFolder>,
+    ) -> Vec<Handle<AudioSource>> {
+        let Some(folder) = self
+            .folders
+            .get(&playlist)
+            .and_then(|handle| folders.get(handle))
+        else {
+            return vec![];
+        };
+        let mut tracks: Vec<Handle<AudioSource>> = folder
+            .handles
+            .iter()
+            .filter_map(|handle| handle.clone().try_typed::<AudioSource>().ok())
+            .collect();
+        tracks.sort_by_key(|handle| handle.path().map(|path| path.to_string()));
+        tracks
+    }
+}
+#[derive(Component)]
+struct MusicTrack {
+    source: AssetId<AudioSource>,
+    fade: f32,
+    fading_out: bool,
+    elapsed: f32,
+}
+pub struct MusicPlugin;
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<MusicSettings>(MUSIC_CONFIG));
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Music".into(),
+                action: MenuAction::Adjust {
+                    value: |world| on_off(world.resource::<MusicSettings>().enabled),
+                    adjust: |world, _| {
+                        let mut settings = world.resource_mut::<MusicSettings>();
+                        settings.enabled = !settings.enabled;
+                        save_config(MUSIC_CONFIG, settings.as_ref());
+                    },
+                },
+                order: 30,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Music Tempo".into(),
+                action: MenuAction::Adjust {
+                    value: |world| on_off(world.resource::<MusicSettings>().tempo_follows_rally),
+                    adjust: |world, _| {
+                        let mut settings = world.resource_mut::<MusicSettings>();
+                        settings.tempo_follows_rally = !settings.tempo_follows_rally;
+                        save_config(MUSIC_CONFIG, settings.as_ref());
+                    },
+                },
+                order: 31,
+            },
+        );
+        if !app.is_plugin_added::<bevy::audio::AudioPlugin>() {
+            return;
+        }
+        app.init_resource::<MusicPlayer>()
+            .add_systems(Startup, load_playlists)
+            .add_systems(OnEnter(AppState::Menu), play_menu_music)
+            .add_systems(OnEnter(AppState::Playing), play_game_music)
+            .add_systems(
+                Update,
+                (
+                    measure_tracks,
+                    rally_intensity.run_if(in_state(AppState::Playing)),
+                    advance_playlist,
+                    fade_tracks,
+                )
+                    .chain(),
+            );
+    }
+}
+fn load_playlists(asset_server: Res<AssetServer>, mut player: ResMut<MusicPlayer>) {
+    for playlist in [Playlist::Menu, Playlist::Game, Playlist::Intense] {
+        if !asset_folder_exists(&asset_server, playlist.folder()) {
+            continue;
+        }
+        let folder = asset_server.load_folder(playlist.folder());
+        player.folders.insert(playlist, folder);
+    }
+}
+fn play_menu_music(
+    mut player: ResMut<MusicPlayer>,
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack)>,
+) {
+    switch_playlist(Playlist::Menu, &mut player, &mut commands, &mut tracks);
+}
+fn play_game_music(
+    mut player: ResMut<MusicPlayer>,
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack)>,
+) {
+    switch_playlist(Playlist::Game, &mut player, &mut commands, &mut tracks);
+}
+fn switch_playlist(
+    playlist: Playlist,
+    player: &mut MusicPlayer,
+    commands: &mut Commands,
+    tracks: &mut Query<(Entity, &mut MusicTrack)>,
+) {
+    if player.playlist == Some(playlist) {
+        return;
+    }
+    player.playlist = Some(playlist);
+    player.track_index = 0;
+    fade_out_all(commands, tracks);
+}
+fn fade_out_all(commands: &mut Commands, tracks: &mut Query<(Entity, &mut MusicTrack)>) {
+    for (entity, mut track) in tracks.iter_mut() {
+        if !track.fading_out {
+            track.fading_out = true;
+            commands.entity(entity).remove::<CurrentTrack>();
+        }
+    }
+}
+#[derive(Component)]
+struct CurrentTrack;
+fn measure_tracks(
+    mut player: ResMut<MusicPlayer>,
+    sources: Res<Assets<AudioSource>>,
+    tracks: Query<&MusicTrack, With<CurrentTrack>>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    for track in tracks.iter() {
+        if player.durations.contains_key(&track.source)
+            || player.measuring.contains_key(&track.source)
+        {
+            continue;
+        }
+        let Some(source) = sources.get(track.source) else {
+            continue;
+        };
+        let source = source.clone();
+        let task = pool.spawn(async move {
+            let decoder = source.decoder();
+            let channels = decoder.channels().max(1) as f32;
+            let sample_rate = decoder.sample_rate().max(1) as f32;
+            Duration::from_secs_f32(decoder.count() as f32 / channels / sample_rate)
+        });
+        player.measuring.insert(track.source, task);
+    }
+    let mut finished = vec![];
+    for (id, task) in player.measuring.iter_mut() {
+        if let Some(duration) = block_on(future::poll_once(task)) {
+            finished.push((*id, duration));
+        }
+    }
+    for (id, duration) in finished {
+        player.measuring.remove(&id);
+        player.durations.insert(id, duration);
+    }
+}
+fn rally_intensity(
+    mut player: ResMut<MusicPlayer>,
+    music_settings: Res<MusicSettings>,
+    settings: Res<Settings>,
+    ball: Query<&Ball>,
+    sinks: Query<&AudioSink, With<CurrentTrack>>,
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack)>,
+) {
+    let speed = ball
+        .get_single()
+        .map(|ball| ball.velocity.length())
+        .unwrap_or(0.);
+    let base_speed = settings.max_spawn_speed.length();
+    let tempo = if music_settings.tempo_follows_rally && speed > base_speed {
+        (1. + (speed / base_speed).log2() * music_settings.tempo_per_speed)
+            .min(music_settings.max_tempo)
+    } else {
+        1.
+    };
+    for sink in sinks.iter() {
+        if (sink.speed() - tempo).abs() > 0.01 {
+            sink.set_speed(tempo);
+        }
+    }
+    let Some(intense_speed) = music_settings.intense_speed else {
+        return;
+    };
+    let wanted = if speed >= intense_speed {
+        Playlist::Intense
+    } else if ball.is_empty() || speed < base_speed {
+        Playlist::Game
+    } else {
+        return;
+    };
+    if player.folders.contains_key(&wanted) {
+        switch_playlist(wanted, &mut player, &mut commands, &mut tracks);
+    }
+}
+fn advance_playlist(
+    mut commands: Commands,
+    mut player: ResMut<MusicPlayer>,
+    music_settings: Res<MusicSettings>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut current: Query<(Entity, &mut MusicTrack, Option<&AudioSink>), With<CurrentTrack>>,
+    time: Res<Time>,
+) {
+    if !music_settings.enabled {
+        for (entity, mut track, _) in current.iter_mut() {
+            track.fading_out = true;
+            commands.entity(entity).remove::<CurrentTrack>();
+        }
+        return;
+    }
+    let Some(playlist) = player.playlist else {
+        return;
+    };
+    if let Ok((entity, mut track, sink)) = current.get_single_mut() {
+        let Some(sink) = sink else {
+            return;
+        };
+        track.elapsed += time.delta_seconds() * sink.speed();
+        let ending = match player.durations.get(&track.source) {
+            Some(duration) => {
+                track.elapsed >= duration.as_secs_f32() - music_settings.crossfade_seconds
+            }
+            None => sink.empty(),
+        };
+        if !ending {
+            return;
+        }
+        track.fading_out = true;
+        commands.entity(entity).remove::<CurrentTrack>();
+        player.track_index += 1;
+    }
+    let tracks = player.tracks(playlist, &folders);
+    if tracks.is_empty() {
+        return;
+    }
+    let source = tracks[player.track_index % tracks.len()].clone();
+    commands.spawn((
+        AudioBundle {
+            source: source.clone(),
+            settings: PlaybackSettings {
+                mode: PlaybackMode::Once,
+                volume: Volume::new(0.),
+                ..Default::default()
+            },
+        },
+        MusicTrack {
+            source: source.id(),
+            fade: 0.,
+            fading_out: false,
+            elapsed: 0.,
+        },
+        CurrentTrack,
+    ));
+}
+fn fade_tracks(
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    music_settings: Res<MusicSettings>,
+    audio_settings: Res<AudioSettings>,
+    time: Res<Time>,
+) {
+    let step = time.delta_seconds() / music_settings.crossfade_seconds.max(0.01);
+    let volume = audio_settings.bus_volume(audio_settings.music_volume);
+    for (entity, mut track, sink) in tracks.iter_mut() {
+        if track.fading_out {
+            track.fade = (track.fade - step).max(0.);
+        } else {
+            track.fade = (track.fade + step).min(1.);
+        }
+        let Some(sink) = sink else {
+            continue;
+        };
+        if track.fading_out && (track.fade <= 0. || sink.empty()) {
+            sink.stop();
+            commands.entity(entity).despawn();
+            continue;
+        }
+        sink.set_volume(volume * track.fade);
+    }
+}
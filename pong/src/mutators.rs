@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use crate::config::{load_config, save_config};
+use crate::game_events::{BallSpawned, GameEventSet, PaddleHit, WallBounce};
+use crate::menu_plugin::{add_menu_entry, on_off, AppState, MenuAction, MenuEntry};
+use crate::paddle_control::{Controller, ControllerKind, PaddleControlSet, PaddleInput};
+use crate::simulation::ball_delta;
+use crate::{Ball, GameData, Paddle, Player, Settings, PLAY_AREA};
+
+const MUTATORS_CONFIG: &str = "mutators";
+/// Seconds the invisible ball shows after a serve or bounce.
+const FLASH_SECONDS: f32 = 0.25;
+/// How much of its height a paddle loses for each point its side has.
+const SHRINK_PER_POINT: f32 = 0.05;
+const MIN_PADDLE_SCALE: f32 = 0.4;
+const GRAVITY: f32 = 250.;
+/// Gravity stops speeding the ball up once it falls this fast.
+const TERMINAL_SPEED: f32 = 600.;
+const WIND_SPEED: f32 = 80.;
+/// Seconds for the wind to blow one way, turn, and come back.
+const WIND_PERIOD: f32 = 12.;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutator {
+    InvisibleBall,
+    ShrinkingPaddles,
+    Gravity,
+    Wind,
+    WrapWalls,
+    MirroredControls,
+}
+impl Mutator {
+    fn name(&self) -> &'static str {
+        match self {
+            Mutator::InvisibleBall => "Invisible Ball",
+            Mutator::ShrinkingPaddles => "Shrinking Paddles",
+            Mutator::Gravity => "Gravity",
+            Mutator::Wind => "Wind",
+            Mutator::WrapWalls => "Wrap Walls",
+            Mutator::MirroredControls => "Mirrored Controls",
+        }
+    }
+}
+/// The mutators switched on, kept between runs.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Mutators(Vec<Mutator>);
+impl Mutators {
+    pub fn is_on(&self, mutator: Mutator) -> bool {
+        self.0.contains(&mutator)
+    }
+    fn toggle(&mut self, mutator: Mutator) {
+        match self.0.iter().position(|on| *on == mutator) {
+            Some(index) => {
+                self.0.remove(index);
+            }
+            None => self.0.push(mutator),
+        }
+    }
+}
+/// Runs a system only while `mutator` is switched on.
+fn mutator_on(mutator: Mutator) -> impl FnMut(Res<Mutators>) -> bool + Clone {
+    move |mutators: Res<Mutators>| mutators.is_on(mutator)
+}
+fn mutator_value(world: &World, mutator: Mutator) -> String {
+    on_off(world.resource::<Mutators>().is_on(mutator))
+}
+fn toggle_mutator(world: &mut World, mutator: Mutator) {
+    let mut mutators = world.resource_mut::<Mutators>();
+    mutators.toggle(mutator);
+    save_config(MUTATORS_CONFIG, mutators.as_ref());
+}
+/// Where mutators change play, after the controllers have set the paddle inputs and before the
+/// paddles and ball move.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MutatorSet;
+/// Adds every mutator, each one its own plugin that hooks into play through [`MutatorSet`] or the
+/// game events, so they can be switched on in any combination.
+pub struct MutatorsPlugin;
+impl Plugin for MutatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<Mutators>(MUTATORS_CONFIG))
+            .configure_sets(
+                Update,
+                MutatorSet
+                    .after(PaddleControlSet)
+                    .run_if(in_state(AppState::Playing)),
+            )
+            .add_plugins((
+                InvisibleBallPlugin,
+                ShrinkingPaddlesPlugin,
+                GravityPlugin,
+                WindPlugin,
+                WrapWallsPlugin,
+                MirroredControlsPlugin,
+            ));
+    }
+}
+/// Hides the ball, showing it briefly when it is served or bounces.
+struct InvisibleBallPlugin;
+#[derive(Resource, Default)]
+struct BallFlash(Timer);
+impl Plugin for InvisibleBallPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BallFlash>().add_systems(
+            Update,
+            flash_ball
+                .after(GameEventSet)
+                .run_if(in_state(AppState::Playing)),
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: Mutator::InvisibleBall.name().into(),
+                action: MenuAction::Adjust {
+                    value: |world| mutator_value(world, Mutator::InvisibleBall),
+                    adjust: |world, _| toggle_mutator(world, Mutator::InvisibleBall),
+                },
+                order: 60,
+            },
+        );
+    }
+}
+fn flash_ball(
+    mutators: Res<Mutators>,
+    mut flash: ResMut<BallFlash>,
+    mut spawned: EventReader<BallSpawned>,
+    mut hits: EventReader<PaddleHit>,
+    mut bounces: EventReader<WallBounce>,
+    mut balls: Query<&mut Visibility, With<Ball>>,
+    time: Res<Time>,
+) {
+    let touched = spawned.read().count() + hits.read().count() + bounces.read().count() > 0;
+    if touched {
+        flash.0 = Timer::from_seconds(FLASH_SECONDS, TimerMode::Once);
+    } else {
+        flash.0.tick(time.delta());
+    }
+    let shown = !mutators.is_on(Mutator::InvisibleBall) || !flash.0.finished();
+    for mut visibility in balls.iter_mut() {
+        *visibility = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+/// Shrinks each paddle as its side's score rises.
+struct ShrinkingPaddlesPlugin;
+impl Plugin for ShrinkingPaddlesPlugin {
+    fn build(&self, app: &mut App) {
+        //Runs all match long, so switching it off grows the paddles back
+        app.add_systems(
+            Update,
+            shrink_paddles
+                .before(MutatorSet)
+                .run_if(in_state(AppState::Playing)),
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: Mutator::ShrinkingPaddles.name().into(),
+                action: MenuAction::Adjust {
+                    value: |world| mutator_value(world, Mutator::ShrinkingPaddles),
+                    adjust: |world, _| toggle_mutator(world, Mutator::ShrinkingPaddles),
+                },
+                order: 61,
+            },
+        );
+    }
+}
+fn shrink_paddles(
+    mutators: Res<Mutators>,
+    game_data: Res<GameData>,
+    mut paddles: Query<(&mut Transform, &Paddle)>,
+) {
+    for (mut transform, paddle) in paddles.iter_mut() {
+        transform.scale.y = if mutators.is_on(Mutator::ShrinkingPaddles) {
+            let score = match paddle.player {
+                Player::Left => game_data.left_score,
+                Player::Right => game_data.right_score,
+            };
+            (1. - score as f32 * SHRINK_PER_POINT).max(MIN_PADDLE_SCALE)
+        } else {
+            1.
+        };
+    }
+}
+/// Pulls the ball towards the bottom wall.
+struct GravityPlugin;
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            apply_gravity
+                .in_set(MutatorSet)
+                .run_if(mutator_on(Mutator::Gravity)),
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: Mutator::Gravity.name().into(),
+                action: MenuAction::Adjust {
+                    value: |world| mutator_value(world, Mutator::Gravity),
+                    adjust: |world, _| toggle_mutator(world, Mutator::Gravity),
+                },
+                order: 62,
+            },
+        );
+    }
+}
+fn apply_gravity(mut balls: Query<&mut Ball>, time: Res<Time>) {
+    for mut ball in balls.iter_mut() {
+        if ball.velocity.y > -TERMINAL_SPEED {
+            ball.velocity.y -= GRAVITY * time.delta_seconds();
+        }
+    }
+}
+/// Drifts the ball across its path in gusts that turn back and forth.
+struct WindPlugin;
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            blow_wind
+                .in_set(MutatorSet)
+                .run_if(mutator_on(Mutator::Wind)),
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: Mutator::Wind.name().into(),
+                action: MenuAction::Adjust {
+                    value: |world| mutator_value(world, Mutator::Wind),
+                    adjust: |world, _| toggle_mutator(world, Mutator::Wind),
+                },
+                order: 63,
+            },
+        );
+    }
+}
+fn blow_wind(
+    mut balls: Query<&mut Transform, With<Ball>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let wind = WIND_SPEED * (time.elapsed_seconds() * TAU / WIND_PERIOD).sin();
+    let max_y = (PLAY_AREA.y - settings.ball_size) / 2.;
+    for mut transform in balls.iter_mut() {
+        transform.translation.y =
+            (transform.translation.y + wind * time.delta_seconds()).clamp(-max_y, max_y);
+    }
+}
+/// Lets the ball leave through the top or bottom wall and come back in through the other.
+struct WrapWallsPlugin;
+impl Plugin for WrapWallsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            wrap_ball
+                .in_set(MutatorSet)
+                .after(apply_gravity)
+                .after(blow_wind)
+                .run_if(mutator_on(Mutator::WrapWalls)),
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: Mutator::WrapWalls.name().into(),
+                action: MenuAction::Adjust {
+                    value: |world| mutator_value(world, Mutator::WrapWalls),
+                    adjust: |world, _| toggle_mutator(world, Mutator::WrapWalls),
+                },
+                order: 64,
+            },
+        );
+    }
+}
+fn wrap_ball(mut balls: Query<(&mut Transform, &Ball)>, settings: Res<Settings>, time: Res<Time>) {
+    //Moves the ball to the far wall before the step that would bounce it
+    let half_height = PLAY_AREA.y / 2.;
+    let span = PLAY_AREA.y - settings.ball_size;
+    for (mut transform, ball) in balls.iter_mut() {
+        let delta_seconds = ball_delta(
+            transform.translation.truncate(),
+            &settings,
+            time.delta_seconds(),
+        );
+        let next_y = transform.translation.y + ball.velocity.y * delta_seconds;
+        if next_y + settings.ball_size / 2. > half_height {
+            transform.translation.y -= span;
+        } else if next_y - settings.ball_size / 2. < -half_height {
+            transform.translation.y += span;
+        }
+    }
+}
+/// Flips the controls of paddles played by people, leaving bots alone.
+struct MirroredControlsPlugin;
+impl Plugin for MirroredControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            mirror_controls
+                .in_set(MutatorSet)
+                .run_if(mutator_on(Mutator::MirroredControls)),
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: Mutator::MirroredControls.name().into(),
+                action: MenuAction::Adjust {
+                    value: |world| mutator_value(world, Mutator::MirroredControls),
+                    adjust: |world, _| toggle_mutator(world, Mutator::MirroredControls),
+                },
+                order: 65,
+            },
+        );
+    }
+}
+fn mirror_controls(mut paddles: Query<(&mut PaddleInput, &Controller)>) {
+    for (mut input, controller) in paddles.iter_mut() {
+        if matches!(
+            controller.0.kind(),
+            ControllerKind::Keyboard
+                | ControllerKind::Mouse
+                | ControllerKind::Gamepad
+                | ControllerKind::Touch
+        ) {
+            input.direction = -input.direction;
+            input.target = input.target.map(|target| -target);
+        }
+    }
+}
@@ -0,0 +1,564 @@
+use bevy::prelude::*;
+use common_modules::debug_text_plugin::{change_debug_text, DebugText};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::config::{load_config, save_config};
+use crate::controllers::{
+    record_inputs, save_recordings, AiController, AiDifficulty, GamepadController, InputRecorder,
+    KeyboardController, MouseController, RemoteController, ReplayController, TouchController,
+};
+use crate::layout_plugin::{Layout, Orientation};
+use crate::menu_plugin::{add_menu_entry, AppState, MenuAction, MenuEntry};
+use crate::script_bot::{bot_names, ScriptController};
+use crate::simulation::PaddleState;
+use crate::{Ball, GameData, MouseCoords, Paddle, Player, Settings, TouchCoords};
+
+const CONTROLS_CONFIG: &str = "controls";
+const RESPONSIVENESS_STEP: f32 = 2.;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerKind {
+    Keyboard,
+    Mouse,
+    Gamepad,
+    Touch,
+    #[serde(rename = "AI")]
+    Ai,
+    Remote,
+    Replay,
+    Script,
+}
+impl ControllerKind {
+    const ALL: [ControllerKind; 8] = [
+        ControllerKind::Keyboard,
+        ControllerKind::Mouse,
+        ControllerKind::Gamepad,
+        ControllerKind::Touch,
+        ControllerKind::Ai,
+        ControllerKind::Remote,
+        ControllerKind::Replay,
+        ControllerKind::Script,
+    ];
+    fn cycle(self, delta: i32) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0) as i32;
+        Self::ALL[(index + delta).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ControllerKind::Keyboard => "Keyboard",
+            ControllerKind::Mouse => "Mouse",
+            ControllerKind::Gamepad => "Gamepad",
+            ControllerKind::Touch => "Touch",
+            ControllerKind::Ai => "AI",
+            ControllerKind::Remote => "Remote",
+            ControllerKind::Replay => "Replay",
+            ControllerKind::Script => "Script",
+        }
+    }
+    pub fn build(&self, player: Player, controls: &PlayerControls) -> Box<dyn PaddleController> {
+        match self {
+            ControllerKind::Keyboard => Box::new(KeyboardController::for_player(player)),
+            ControllerKind::Mouse => Box::new(MouseController),
+            ControllerKind::Gamepad => Box::new(GamepadController),
+            ControllerKind::Touch => Box::new(TouchController),
+            ControllerKind::Ai => Box::new(AiController::new(
+                controls.ai_difficulty,
+                rand::thread_rng().gen(),
+            )),
+            ControllerKind::Remote => {
+                Box::new(RemoteController::bind(player, controls.remote_address))
+            }
+            ControllerKind::Replay => Box::new(ReplayController::load(player)),
+            ControllerKind::Script => Box::new(ScriptController::load(controls.bot(player))),
+        }
+    }
+}
+/// Which controller drives each paddle, how the AI and script controllers play, and how
+/// eagerly paddles chase a target.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PlayerControls {
+    pub left: ControllerKind,
+    pub right: ControllerKind,
+    pub ai_difficulty: AiDifficulty,
+    pub left_bot: String,
+    pub right_bot: String,
+    pub follow: FollowTuning,
+    /// Address the remote controller listens on. Only this machine can send input unless it is
+    /// widened, for example to `0.0.0.0` to take input from the network.
+    pub remote_address: IpAddr,
+}
+impl Default for PlayerControls {
+    fn default() -> Self {
+        Self {
+            left: ControllerKind::Keyboard,
+            right: ControllerKind::Keyboard,
+            ai_difficulty: AiDifficulty::default(),
+            left_bot: "follow".into(),
+            right_bot: "follow".into(),
+            follow: FollowTuning::default(),
+            remote_address: Ipv4Addr::LOCALHOST.into(),
+        }
+    }
+}
+impl PlayerControls {
+    pub fn kind(&self, player: Player) -> ControllerKind {
+        match player {
+            Player::Left => self.left,
+            Player::Right => self.right,
+        }
+    }
+    fn kind_mut(&mut self, player: Player) -> &mut ControllerKind {
+        match player {
+            Player::Left => &mut self.left,
+            Player::Right => &mut self.right,
+        }
+    }
+    pub fn bot(&self, player: Player) -> &str {
+        match player {
+            Player::Left => &self.left_bot,
+            Player::Right => &self.right_bot,
+        }
+    }
+    fn bot_mut(&mut self, player: Player) -> &mut String {
+        match player {
+            Player::Left => &mut self.left_bot,
+            Player::Right => &mut self.right_bot,
+        }
+    }
+}
+/// What a controller asks of its paddle this frame. With a `target` the paddle follows it and
+/// `direction` is ignored.
+#[derive(Component, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct PaddleInput {
+    /// -1 to 1, positive moves up the court.
+    pub direction: f32,
+    pub target: Option<f32>,
+}
+impl PaddleInput {
+    pub fn direction(direction: f32) -> Self {
+        Self {
+            direction: direction.clamp(-1., 1.),
+            target: None,
+        }
+    }
+    pub fn target(target: f32) -> Self {
+        Self {
+            direction: 0.,
+            target: Some(target),
+        }
+    }
+}
+/// Ball state in court coordinates.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct BallState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+#[derive(Clone, Copy)]
+pub struct GamepadInput<'a> {
+    pub gamepads: &'a Gamepads,
+    pub axes: &'a Axis<GamepadAxis>,
+    pub buttons: &'a ButtonInput<GamepadButton>,
+}
+/// Everything a controller may look at when deciding its input. Positions are in court
+/// coordinates, with the paddles on the x axis.
+pub struct ControllerContext<'a> {
+    pub player: Player,
+    pub ball: Option<BallState>,
+    /// Left and right paddle y.
+    pub paddles: [f32; 2],
+    pub orientation: Orientation,
+    pub keyboard: &'a ButtonInput<KeyCode>,
+    pub cursor: Option<Vec2>,
+    pub touches: &'a [Vec2],
+    pub gamepad: GamepadInput<'a>,
+    pub settings: &'a Settings,
+    pub delta_seconds: f32,
+}
+pub trait PaddleController: Send + Sync {
+    fn kind(&self) -> ControllerKind;
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput;
+    /// Shown on the debug overlay while the controller is failing.
+    fn error(&self) -> Option<&str> {
+        None
+    }
+}
+/// Writes every paddle's [`PaddleInput`] for the frame.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PaddleControlSet;
+/// The controller driving a paddle. Swapped when [`PlayerControls`] changes.
+#[derive(Component)]
+pub struct Controller(pub Box<dyn PaddleController>);
+/// PD controller gains for following a target y.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FollowTuning {
+    /// Natural frequency in radians per second, higher is snappier.
+    pub responsiveness: f32,
+    /// 1 is critically damped, lower overshoots, higher creeps in.
+    pub damping_ratio: f32,
+}
+impl Default for FollowTuning {
+    fn default() -> Self {
+        Self {
+            responsiveness: 12.,
+            damping_ratio: 1.,
+        }
+    }
+}
+impl FollowTuning {
+    /// Input direction in -1..=1 that steers a paddle at `position` moving at `speed` towards
+    /// `target`, given the paddle's maximum acceleration.
+    pub fn direction(&self, position: f32, speed: f32, target: f32, max_acceleration: f32) -> f32 {
+        let stiffness = self.responsiveness.powi(2);
+        let damping = 2. * self.damping_ratio * self.responsiveness;
+        let acceleration = stiffness * (target - position) - damping * speed;
+        if !acceleration.is_finite() || max_acceleration <= 0. {
+            return 0.;
+        }
+        (acceleration / max_acceleration).clamp(-1., 1.)
+    }
+}
+/// Advances a paddle's speed and position by one step. Friction only slows paddles that aren't
+/// following a target, followed paddles are braked by the controller's damping instead.
+pub fn move_paddle(
+    paddle: PaddleState,
+    direction: f32,
+    following: bool,
+    acceleration: f32,
+    max_speed: f32,
+    friction: f32,
+    delta_seconds: f32,
+) -> PaddleState {
+    let mut speed =
+        (paddle.speed + direction * acceleration * delta_seconds).clamp(-max_speed, max_speed);
+    if !following {
+        if speed > 0. {
+            speed = (speed - friction * delta_seconds).max(0.);
+        } else if speed < 0. {
+            speed = (speed + friction * delta_seconds).min(0.);
+        }
+    }
+    PaddleState {
+        y: paddle.y + speed * delta_seconds,
+        speed,
+    }
+}
+pub struct PaddleControlPlugin;
+impl Plugin for PaddleControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<PlayerControls>(CONTROLS_CONFIG))
+            .init_resource::<InputRecorder>()
+            .add_systems(
+                Update,
+                (
+                    swap_controllers,
+                    run_controllers
+                        .run_if(in_state(AppState::Playing).or_else(in_state(AppState::Practice))),
+                    record_inputs.run_if(in_state(AppState::Playing)),
+                )
+                    .chain()
+                    .in_set(PaddleControlSet),
+            )
+            .add_systems(OnEnter(AppState::Summary), save_recordings);
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Left Paddle".into(),
+                action: MenuAction::Adjust {
+                    value: |world| kind_name(world, Player::Left),
+                    adjust: |world, delta| cycle_kind(world, Player::Left, delta),
+                },
+                order: 2,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Right Paddle".into(),
+                action: MenuAction::Adjust {
+                    value: |world| kind_name(world, Player::Right),
+                    adjust: |world, delta| cycle_kind(world, Player::Right, delta),
+                },
+                order: 3,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "AI Difficulty".into(),
+                action: MenuAction::Adjust {
+                    value: |world| {
+                        world
+                            .resource::<PlayerControls>()
+                            .ai_difficulty
+                            .name()
+                            .into()
+                    },
+                    adjust: |world, delta| {
+                        adjust_controls(world, |controls| {
+                            let all = AiDifficulty::ALL;
+                            let index = all
+                                .iter()
+                                .position(|difficulty| *difficulty == controls.ai_difficulty)
+                                .unwrap_or(0) as i32;
+                            controls.ai_difficulty =
+                                all[(index + delta).rem_euclid(all.len() as i32) as usize];
+                        })
+                    },
+                },
+                order: 4,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Left Bot".into(),
+                action: MenuAction::Adjust {
+                    value: |world| world.resource::<PlayerControls>().left_bot.clone(),
+                    adjust: |world, delta| cycle_bot(world, Player::Left, delta),
+                },
+                order: 5,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Right Bot".into(),
+                action: MenuAction::Adjust {
+                    value: |world| world.resource::<PlayerControls>().right_bot.clone(),
+                    adjust: |world, delta| cycle_bot(world, Player::Right, delta),
+                },
+                order: 6,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Follow Response".into(),
+                action: MenuAction::Adjust {
+                    value: |world| {
+                        format!(
+                            "{:.0}",
+                            world.resource::<PlayerControls>().follow.responsiveness
+                        )
+                    },
+                    adjust: |world, delta| {
+                        adjust_controls(world, |controls| {
+                            controls.follow.responsiveness = (controls.follow.responsiveness
+                                + delta as f32 * RESPONSIVENESS_STEP)
+                                .clamp(RESPONSIVENESS_STEP, 40.);
+                        })
+                    },
+                },
+                order: 7,
+            },
+        );
+    }
+}
+fn kind_name(world: &World, player: Player) -> String {
+    world
+        .resource::<PlayerControls>()
+        .kind(player)
+        .name()
+        .into()
+}
+fn cycle_kind(world: &mut World, player: Player, delta: i32) {
+    adjust_controls(world, |controls| {
+        let kind = controls.kind_mut(player);
+        *kind = kind.cycle(delta);
+    });
+}
+fn cycle_bot(world: &mut World, player: Player, delta: i32) {
+    let names = bot_names();
+    if names.is_empty() {
+        return;
+    }
+    adjust_controls(world, |controls| {
+        let bot = controls.bot_mut(player);
+        let index = names
+            .iter()
+            .position(|name| name == bot)
+            .map_or(0, |index| {
+                (index as i32 + delta).rem_euclid(names.len() as i32) as usize
+            });
+        *bot = names[index].clone();
+    });
+}
+fn adjust_controls(world: &mut World, change: impl FnOnce(&mut PlayerControls)) {
+    let mut controls = world.resource_mut::<PlayerControls>();
+    change(&mut controls);
+    save_config(CONTROLS_CONFIG, controls.as_ref());
+}
+fn swap_controllers(
+    mut commands: Commands,
+    controls: Res<PlayerControls>,
+    paddles: Query<(Entity, &Paddle, Option<&Controller>)>,
+) {
+    for (entity, paddle, controller) in paddles.iter() {
+        let kind = controls.kind(paddle.player);
+        //AI and script controllers are also rebuilt when their difficulty or bot may have
+        //changed, which reloads the bot
+        let configured = matches!(kind, ControllerKind::Ai | ControllerKind::Script);
+        if controller.is_some_and(|controller| {
+            controller.0.kind() == kind && !(configured && controls.is_changed())
+        }) {
+            continue;
+        }
+        commands.entity(entity).insert((
+            Controller(kind.build(paddle.player, &controls)),
+            PaddleInput::default(),
+        ));
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn run_controllers(
+    mut paddles: Query<(&Paddle, &mut Controller, &mut PaddleInput)>,
+    ball: Query<(&Transform, &Ball)>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_pos: Res<MouseCoords>,
+    touch_coords: Res<TouchCoords>,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    layout: Res<Layout>,
+    settings: Res<Settings>,
+    game_data: Res<GameData>,
+    time: Res<Time>,
+    mut debug_text: ResMut<DebugText>,
+) {
+    let ball = ball.get_single().ok().map(|(transform, ball)| BallState {
+        position: transform.translation.truncate(),
+        velocity: ball.velocity,
+    });
+    for (paddle, mut controller, mut input) in paddles.iter_mut() {
+        let context = ControllerContext {
+            player: paddle.player,
+            ball,
+            paddles: [game_data.left_y, game_data.right_y],
+            orientation: layout.orientation,
+            keyboard: &keyboard,
+            cursor: mouse_pos.0,
+            touches: &touch_coords.0,
+            gamepad: GamepadInput {
+                gamepads: &gamepads,
+                axes: &gamepad_axes,
+                buttons: &gamepad_buttons,
+            },
+            settings: &settings,
+            delta_seconds: time.delta_seconds(),
+        };
+        *input = controller.0.update(&context);
+        if controller.0.kind() == ControllerKind::Script {
+            change_debug_text(
+                &mut debug_text,
+                match paddle.player {
+                    Player::Left => "Left Bot",
+                    Player::Right => "Right Bot",
+                },
+                controller.0.error().unwrap_or("OK"),
+            );
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCELERATION: f32 = 3000.;
+    const MAX_SPEED: f32 = 500.;
+    const DT: f32 = 1. / 60.;
+
+    fn follow(tuning: FollowTuning, start: f32, target: f32, steps: usize) -> Vec<(f32, f32)> {
+        let mut state = PaddleState {
+            y: start,
+            speed: 0.,
+        };
+        (0..steps)
+            .map(|_| {
+                let direction = tuning.direction(state.y, state.speed, target, ACCELERATION);
+                state = move_paddle(state, direction, true, ACCELERATION, MAX_SPEED, 700., DT);
+                (state.y, state.speed)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn level_with_target_at_rest_stays_put() {
+        let direction = FollowTuning::default().direction(25., 0., 25., ACCELERATION);
+        assert_eq!(direction, 0.);
+    }
+
+    #[test]
+    fn target_below_pushes_down() {
+        let direction = FollowTuning::default().direction(100., 0., -50., ACCELERATION);
+        assert_eq!(direction, -1.);
+        let direction = FollowTuning::default().direction(0., 0., -0.5, ACCELERATION);
+        assert!(direction < 0. && direction > -1.);
+    }
+
+    #[test]
+    fn moving_towards_target_brakes_before_reaching_it() {
+        let direction = FollowTuning::default().direction(-5., MAX_SPEED, 0., ACCELERATION);
+        assert!(direction < 0.);
+    }
+
+    #[test]
+    fn invalid_input_gives_no_direction() {
+        let tuning = FollowTuning::default();
+        assert_eq!(tuning.direction(0., 0., f32::NAN, ACCELERATION), 0.);
+        assert_eq!(tuning.direction(0., 0., f32::INFINITY, ACCELERATION), 0.);
+        assert_eq!(tuning.direction(0., 0., 10., 0.), 0.);
+    }
+
+    #[test]
+    fn converges_on_target_without_large_overshoot() {
+        for (start, target) in [(0., 150.), (180., -180.), (-40., -39.)] {
+            let path = follow(FollowTuning::default(), start, target, 120);
+            let (position, speed) = *path.last().unwrap();
+            assert!(
+                (position - target).abs() < 0.5,
+                "{start} -> {target}: {position}"
+            );
+            assert!(speed.abs() < 1., "{start} -> {target}: {speed}");
+            let overshoot = path
+                .iter()
+                .map(|(position, _)| (position - target) * (target - start).signum())
+                .fold(0., f32::max);
+            assert!(
+                overshoot < 2.,
+                "{start} -> {target} overshot by {overshoot}"
+            );
+        }
+    }
+
+    #[test]
+    fn higher_responsiveness_arrives_sooner() {
+        let steps_to_arrive = |responsiveness| {
+            let tuning = FollowTuning {
+                responsiveness,
+                damping_ratio: 1.,
+            };
+            follow(tuning, 0., 20., 240)
+                .iter()
+                .position(|(position, _)| (position - 20.).abs() < 1.)
+                .unwrap()
+        };
+        assert!(steps_to_arrive(20.) < steps_to_arrive(6.));
+    }
+
+    #[test]
+    fn friction_only_slows_free_paddles() {
+        let moving = |speed| PaddleState { y: 0., speed };
+        let free = move_paddle(moving(100.), 0., false, ACCELERATION, MAX_SPEED, 700., DT);
+        let following = move_paddle(moving(100.), 0., true, ACCELERATION, MAX_SPEED, 700., DT);
+        assert!(free.speed < 100.);
+        assert_eq!(following.speed, 100.);
+        let stopped = move_paddle(moving(5.), 0., false, ACCELERATION, MAX_SPEED, 700., DT);
+        assert_eq!(stopped.speed, 0.);
+    }
+}
@@ -0,0 +1,576 @@
+use bevy::math::{vec2, vec3};
+use bevy::prelude::*;
+use common_modules::bitmap_font_plugin::{BitmapText, BitmapTextBundle};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::game_events::{GameEventSet, PaddleHit, WallBounce};
+use crate::layout_plugin::{CourtRoot, Layout};
+use crate::match_stats::reset_match;
+use crate::menu_plugin::{add_menu_entry, despawn_screen, AppState, MenuAction, MenuEntry};
+use crate::paddle_control::PaddleControlSet;
+use crate::simulation::{serve, step_ball};
+use crate::theme_plugin::{ThemeRole, Themed};
+use crate::{
+    accelerate_paddles, insert_ball, spawn_square, Ball, GameData, Paddle, Player, ServeSeed,
+    Settings, PLAY_AREA,
+};
+
+pub const LEVEL_DIR: &str = "./levels";
+const LEVEL_EXTENSION: &str = "json";
+const STARTING_LIVES: u32 = 3;
+const BRICK_WIDTH: f32 = 20.;
+const BRICK_GAP: f32 = 2.;
+const POWER_UP_SIZE: f32 = 12.;
+/// How fast a dropped power-up drifts towards the paddle.
+const POWER_UP_SPEED: f32 = 150.;
+const WIDEN_SCALE: f32 = 1.5;
+const WIDEN_SECONDS: f32 = 10.;
+/// Speed the ball drops to when a slow ball power-up is caught.
+const SLOW_SPEED: f32 = 300.;
+/// Angle in radians between the balls a multi-ball power-up splits into.
+const SPLIT_ANGLE: f32 = 0.5;
+/// Keeps the unused right paddle out of reach of the ball.
+const PARKED_PADDLE_Y: f32 = 10_000.;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum PowerUp {
+    /// Stretches the paddle for a while.
+    Widen,
+    SlowBall,
+    /// Splits every ball in play in two.
+    MultiBall,
+    ExtraLife,
+}
+/// A kind of brick, keyed by the character that places it in a level's layout.
+#[derive(Deserialize, Debug, Clone, Copy)]
+struct BrickKind {
+    hit_points: u32,
+    score: u32,
+    /// Dropped when the brick breaks.
+    #[serde(default)]
+    power_up: Option<PowerUp>,
+}
+/// A wall of bricks read from the levels folder. Each layout line is a row of bricks from the top
+/// of the court, with the last character against the right wall and spaces or dots left empty.
+#[derive(Deserialize, Debug)]
+struct Level {
+    name: String,
+    bricks: HashMap<char, BrickKind>,
+    layout: Vec<String>,
+}
+/// Paths of the levels in the levels folder, played in file name order.
+fn level_paths() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(LEVEL_DIR) else {
+        return vec![];
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == LEVEL_EXTENSION))
+        .collect();
+    paths.sort();
+    paths
+}
+fn load_level(path: &Path) -> Result<Level, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&contents).map_err(|err| err.to_string())
+}
+#[derive(Component)]
+struct Brick {
+    hit_points: u32,
+    score: u32,
+    power_up: Option<PowerUp>,
+    size: Vec2,
+}
+#[derive(Component)]
+struct Dropped(PowerUp);
+#[derive(Component)]
+struct PracticeHud;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunState {
+    Playing,
+    GameOver,
+    /// Every level has been cleared.
+    Complete,
+}
+/// The practice run in progress.
+#[derive(Resource)]
+struct PracticeRun {
+    levels: Vec<PathBuf>,
+    level: usize,
+    level_name: String,
+    score: u32,
+    lives: u32,
+    state: RunState,
+    serve_timer: Timer,
+    widen_timer: Timer,
+}
+impl Default for PracticeRun {
+    fn default() -> Self {
+        Self {
+            levels: level_paths(),
+            level: 0,
+            level_name: String::new(),
+            score: 0,
+            lives: STARTING_LIVES,
+            state: RunState::Playing,
+            serve_timer: Timer::from_seconds(1., TimerMode::Once),
+            widen_timer: Timer::new(Duration::ZERO, TimerMode::Once),
+        }
+    }
+}
+/// A solo mode where the right goal is replaced by a wall of bricks to break with the left
+/// paddle, using the same paddle and ball physics as a match.
+pub struct PracticePlugin;
+impl Plugin for PracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PracticeRun>()
+            .add_systems(
+                OnEnter(AppState::Practice),
+                (reset_match, start_practice).chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    practice_input,
+                    (
+                        update_practice_ball.in_set(GameEventSet),
+                        move_power_ups,
+                        serve_practice_ball,
+                    )
+                        .chain()
+                        .after(PaddleControlSet)
+                        .after(accelerate_paddles),
+                    update_hud,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Practice)),
+            )
+            .add_systems(
+                OnExit(AppState::Practice),
+                (
+                    despawn_screen::<Brick>,
+                    despawn_screen::<Dropped>,
+                    despawn_screen::<PracticeHud>,
+                    end_practice,
+                    reset_match,
+                )
+                    .chain(),
+            );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Practice".into(),
+                action: MenuAction::Activate(|world| {
+                    world
+                        .resource_mut::<NextState<AppState>>()
+                        .set(AppState::Practice)
+                }),
+                order: 18,
+            },
+        );
+    }
+}
+fn start_practice(
+    mut commands: Commands,
+    mut run: ResMut<PracticeRun>,
+    mut paddles: Query<(&mut Visibility, &Paddle)>,
+    layout: Res<Layout>,
+    court: Res<CourtRoot>,
+) {
+    *run = PracticeRun::default();
+    for (mut visibility, paddle) in paddles.iter_mut() {
+        if paddle.player == Player::Right {
+            *visibility = Visibility::Hidden;
+        }
+    }
+    commands.spawn((
+        BitmapTextBundle::new(
+            BitmapText::new("", 2.),
+            vec3(0., layout.view_size().y / 2. - 12., 6.),
+        ),
+        Themed(ThemeRole::Accent),
+        PracticeHud,
+    ));
+    spawn_level(&mut commands, &mut run, court.0);
+}
+fn end_practice(mut paddles: Query<(&mut Visibility, &mut Transform), With<Paddle>>) {
+    for (mut visibility, mut transform) in paddles.iter_mut() {
+        *visibility = Visibility::Inherited;
+        transform.scale.y = 1.;
+    }
+}
+/// Builds the current level's wall, or ends the run when there are no levels left.
+fn spawn_level(commands: &mut Commands, run: &mut PracticeRun, court: Entity) {
+    let Some(path) = run.levels.get(run.level) else {
+        run.state = RunState::Complete;
+        return;
+    };
+    let level = match load_level(path) {
+        Ok(level) => level,
+        Err(err) => {
+            eprintln!("Error: failed to load {:?}: {err}", path);
+            run.level += 1;
+            return spawn_level(commands, run, court);
+        }
+    };
+    run.level_name = level.name.to_uppercase();
+    let rows = level.layout.len().max(1);
+    let brick_height = PLAY_AREA.y / rows as f32;
+    for (row, line) in level.layout.iter().enumerate() {
+        let columns = line.chars().count();
+        for (column, key) in line.chars().enumerate() {
+            let Some(kind) = level.bricks.get(&key) else {
+                continue;
+            };
+            let size = vec2(BRICK_WIDTH, brick_height) - BRICK_GAP;
+            let position = vec2(
+                PLAY_AREA.x / 2. - (columns - column) as f32 * BRICK_WIDTH + BRICK_WIDTH / 2.,
+                PLAY_AREA.y / 2. - (row as f32 + 0.5) * brick_height,
+            );
+            let brick = spawn_square(size, position.y, commands, court);
+            commands.entity(brick).insert((
+                Transform::from_translation(position.extend(0.)),
+                Themed(brick_role(kind.power_up, kind.hit_points)),
+                Brick {
+                    hit_points: kind.hit_points.max(1),
+                    score: kind.score,
+                    power_up: kind.power_up,
+                    size,
+                },
+            ));
+        }
+    }
+}
+/// Power-up bricks stand out, and tough bricks look different until they are down to one hit.
+fn brick_role(power_up: Option<PowerUp>, hit_points: u32) -> ThemeRole {
+    match (power_up, hit_points) {
+        (Some(_), _) => ThemeRole::Accent,
+        (None, 0 | 1) => ThemeRole::Ball,
+        (None, _) => ThemeRole::Paddle(Player::Right),
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn serve_practice_ball(
+    mut commands: Commands,
+    mut run: ResMut<PracticeRun>,
+    balls: Query<(), With<Ball>>,
+    settings: Res<Settings>,
+    game_data: Res<GameData>,
+    mut serve_seed: ResMut<ServeSeed>,
+    time: Res<Time>,
+    court: Res<CourtRoot>,
+) {
+    if run.state != RunState::Playing
+        || !balls.is_empty()
+        || !run.serve_timer.tick(time.delta()).finished()
+    {
+        return;
+    }
+    run.serve_timer.reset();
+    //Served off the paddle, always towards the bricks
+    let (_, velocity) = serve(&mut serve_seed.next_rng(), &settings, None);
+    let position = vec2(
+        -settings.paddle_x + settings.ball_size * 3.,
+        game_data.left_y,
+    );
+    insert_ball(
+        &mut commands,
+        &settings,
+        position,
+        vec2(velocity.x.abs(), velocity.y),
+        court.0,
+    );
+}
+#[allow(clippy::too_many_arguments)]
+fn update_practice_ball(
+    mut commands: Commands,
+    mut run: ResMut<PracticeRun>,
+    mut balls: Query<(Entity, &mut Ball, &mut Transform), Without<Brick>>,
+    mut bricks: Query<(Entity, &mut Brick, &mut Themed, &Transform)>,
+    paddles: Query<(&Transform, &Paddle), Without<Ball>>,
+    settings: Res<Settings>,
+    game_data: Res<GameData>,
+    time: Res<Time>,
+    court: Res<CourtRoot>,
+    mut hits: EventWriter<PaddleHit>,
+    mut bounces: EventWriter<WallBounce>,
+) {
+    if run.state != RunState::Playing {
+        return;
+    }
+    let paddle_scale = paddles
+        .iter()
+        .find(|(_, paddle)| paddle.player == Player::Left)
+        .map_or(1., |(transform, _)| transform.scale.y);
+    let mut bricks_left = bricks.iter().count();
+    let mut balls_left = balls.iter().count();
+    let half = PLAY_AREA / 2.;
+    let ball_half = settings.ball_size / 2.;
+    for (entity, mut ball, mut transform) in balls.iter_mut() {
+        let mut step = step_ball(
+            transform.translation.truncate(),
+            ball.velocity,
+            [game_data.left_y, PARKED_PADDLE_Y],
+            [paddle_scale, 1.],
+            [0; 2],
+            &settings,
+            time.delta_seconds(),
+        );
+        if let Some(hit) = step.hit {
+            hits.send(PaddleHit {
+                player: hit.player,
+                position: hit.position,
+                speed: hit.speed,
+                offset: hit.impact_offset,
+            });
+        }
+        //The right goal is a solid wall
+        if step.position.x + ball_half > half.x {
+            step.position.x = half.x - ball_half;
+            step.velocity.x = -step.velocity.x.abs();
+            step.scorer = None;
+            step.bounced = true;
+        }
+        if step.scorer.is_some() {
+            commands.entity(entity).despawn_recursive();
+            balls_left -= 1;
+            continue;
+        }
+        if let Some((brick_entity, mut brick, mut themed, brick_transform)) =
+            bricks.iter_mut().find(|(_, brick, _, brick_transform)| {
+                let gap = (step.position - brick_transform.translation.truncate()).abs();
+                //A brick broken earlier this frame is only despawned once the commands run
+                brick.hit_points > 0
+                    && gap.x < brick.size.x / 2. + ball_half
+                    && gap.y < brick.size.y / 2. + ball_half
+            })
+        {
+            //Bounce off the side the ball went in least
+            let offset = step.position - brick_transform.translation.truncate();
+            let overlap = brick.size / 2. + ball_half - offset.abs();
+            if overlap.x < overlap.y {
+                step.velocity.x = step.velocity.x.abs() * offset.x.signum();
+            } else {
+                step.velocity.y = step.velocity.y.abs() * offset.y.signum();
+            }
+            step.bounced = true;
+            brick.hit_points -= 1;
+            if brick.hit_points == 0 {
+                run.score += brick.score;
+                bricks_left -= 1;
+                commands.entity(brick_entity).despawn_recursive();
+                if let Some(power_up) = brick.power_up {
+                    let square = spawn_square(
+                        Vec2::splat(POWER_UP_SIZE),
+                        brick_transform.translation.y,
+                        &mut commands,
+                        court.0,
+                    );
+                    commands.entity(square).insert((
+                        Transform::from_translation(brick_transform.translation),
+                        Themed(ThemeRole::Accent),
+                        Dropped(power_up),
+                    ));
+                }
+            } else {
+                themed.0 = brick_role(brick.power_up, brick.hit_points);
+            }
+        }
+        if step.bounced {
+            bounces.send(WallBounce {
+                position: step.position,
+                speed: step.velocity.length(),
+            });
+        }
+        ball.velocity = step.velocity;
+        transform.translation.x = step.position.x;
+        transform.translation.y = step.position.y;
+    }
+    if bricks_left == 0 {
+        //On to the next level, served afresh
+        for (entity, ..) in balls.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        run.level += 1;
+        run.serve_timer.reset();
+        spawn_level(&mut commands, &mut run, court.0);
+    } else if balls_left == 0 && !balls.is_empty() {
+        run.lives = run.lives.saturating_sub(1);
+        if run.lives == 0 {
+            run.state = RunState::GameOver;
+        }
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn move_power_ups(
+    mut commands: Commands,
+    mut run: ResMut<PracticeRun>,
+    mut dropped: Query<(Entity, &Dropped, &mut Transform), Without<Paddle>>,
+    mut paddles: Query<(&mut Transform, &Paddle), Without<Ball>>,
+    mut balls: Query<(&mut Ball, &Transform), Without<Dropped>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    court: Res<CourtRoot>,
+) {
+    let Some((mut paddle, _)) = paddles
+        .iter_mut()
+        .find(|(_, paddle)| paddle.player == Player::Left)
+    else {
+        return;
+    };
+    if run.widen_timer.tick(time.delta()).finished() {
+        paddle.scale.y = 1.;
+    }
+    let paddle_size = settings.player_paddle_size(Player::Left) * vec2(1., paddle.scale.y);
+    for (entity, power_up, mut transform) in dropped.iter_mut() {
+        transform.translation.x -= POWER_UP_SPEED * time.delta_seconds();
+        let gap = (transform.translation - paddle.translation)
+            .truncate()
+            .abs();
+        let caught = gap.x < (paddle_size.x + POWER_UP_SIZE) / 2.
+            && gap.y < (paddle_size.y + POWER_UP_SIZE) / 2.;
+        if !caught {
+            if transform.translation.x < -PLAY_AREA.x / 2. {
+                commands.entity(entity).despawn_recursive();
+            }
+            continue;
+        }
+        commands.entity(entity).despawn_recursive();
+        match power_up.0 {
+            PowerUp::Widen => {
+                paddle.scale.y = WIDEN_SCALE;
+                run.widen_timer = Timer::from_seconds(WIDEN_SECONDS, TimerMode::Once);
+            }
+            PowerUp::SlowBall => {
+                for (mut ball, _) in balls.iter_mut() {
+                    ball.velocity = ball.velocity.normalize_or_zero() * SLOW_SPEED;
+                }
+            }
+            PowerUp::MultiBall => {
+                //Each ball turns one way and a new one heads off the other
+                for (mut ball, ball_transform) in balls.iter_mut() {
+                    insert_ball(
+                        &mut commands,
+                        &settings,
+                        ball_transform.translation.truncate(),
+                        Vec2::from_angle(SPLIT_ANGLE / 2.).rotate(ball.velocity),
+                        court.0,
+                    );
+                    ball.velocity = Vec2::from_angle(-SPLIT_ANGLE / 2.).rotate(ball.velocity);
+                }
+            }
+            PowerUp::ExtraLife => run.lives += 1,
+        }
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn practice_input(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut run: ResMut<PracticeRun>,
+    mut next_state: ResMut<NextState<AppState>>,
+    bricks: Query<Entity, With<Brick>>,
+    dropped: Query<Entity, With<Dropped>>,
+    balls: Query<Entity, With<Ball>>,
+    court: Res<CourtRoot>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+    } else if run.state != RunState::Playing && keyboard_input.just_pressed(KeyCode::Enter) {
+        for entity in bricks.iter().chain(dropped.iter()).chain(balls.iter()) {
+            commands.entity(entity).despawn_recursive();
+        }
+        *run = PracticeRun::default();
+        spawn_level(&mut commands, &mut run, court.0);
+    }
+}
+fn update_hud(run: Res<PracticeRun>, mut hud: Query<&mut BitmapText, With<PracticeHud>>) {
+    if !run.is_changed() {
+        return;
+    }
+    let status = format!(
+        "LEVEL {}: {}  SCORE {}  LIVES {}",
+        run.level + 1,
+        run.level_name,
+        run.score,
+        run.lives
+    );
+    let text = match run.state {
+        RunState::Playing => status,
+        RunState::GameOver => format!("{status}\nGAME OVER  ENTER: RETRY  ESC: MENU"),
+        RunState::Complete if run.levels.is_empty() => {
+            format!("NO LEVELS IN {LEVEL_DIR}\nESC: MENU")
+        }
+        RunState::Complete => {
+            format!(
+                "ALL LEVELS CLEARED  SCORE {}\nENTER: AGAIN  ESC: MENU",
+                run.score
+            )
+        }
+    };
+    for mut hud in hud.iter_mut() {
+        hud.text.clone_from(&text);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_brick(world: &mut World, position: Vec2) -> Entity {
+        world
+            .spawn((
+                Transform::from_translation(position.extend(0.)),
+                Themed(ThemeRole::Ball),
+                Brick {
+                    hit_points: 1,
+                    score: 10,
+                    power_up: None,
+                    size: vec2(BRICK_WIDTH, 40.),
+                },
+            ))
+            .id()
+    }
+
+    #[test]
+    fn overlapping_balls_break_a_brick_once() {
+        let mut app = App::new();
+        app.add_event::<PaddleHit>()
+            .add_event::<WallBounce>()
+            .init_resource::<PracticeRun>()
+            .init_resource::<Settings>()
+            .init_resource::<GameData>()
+            .init_resource::<Time>()
+            .init_resource::<CourtRoot>()
+            .add_systems(Update, update_practice_ball);
+        let brick = spawn_brick(&mut app.world, vec2(200., 0.));
+        //Keeps the level from counting as cleared
+        spawn_brick(&mut app.world, vec2(200., 150.));
+        //Both halves of a multi-ball split, on top of each other and inside the brick
+        for _ in 0..2 {
+            app.world.spawn((
+                Transform::from_xyz(195., 0., 0.),
+                Ball {
+                    velocity: vec2(300., 0.),
+                },
+            ));
+        }
+        app.update();
+        assert!(app.world.get_entity(brick).is_none());
+        assert_eq!(app.world.resource::<PracticeRun>().score, 10);
+        let bounces = app.world.resource::<Events<WallBounce>>();
+        assert_eq!(bounces.get_reader().read(bounces).count(), 1);
+        let velocities: Vec<Vec2> = app
+            .world
+            .query::<&Ball>()
+            .iter(&app.world)
+            .map(|ball| ball.velocity)
+            .collect();
+        assert_eq!(velocities.len(), 2);
+        assert!(velocities.contains(&vec2(300., 0.)));
+    }
+}
@@ -0,0 +1,477 @@
+use bevy::math::vec3;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use common_modules::bitmap_font_plugin::{glyph_index, BitmapText, BitmapTextBundle};
+use serde::{Deserialize, Serialize};
+
+use crate::achievements::Achievement;
+use crate::config::{load_config, save_config};
+use crate::layout_plugin::Layout;
+use crate::match_stats::MatchStats;
+use crate::menu_plugin::{
+    add_menu_entry, despawn_screen, spawn_screen, AppState, MenuAction, MenuEntry,
+};
+use crate::Player;
+
+const PROFILES_CONFIG: &str = "profiles";
+const STARTING_RATING: f32 = 1200.;
+/// Most rating points that can change hands in one match.
+const RATING_K: f32 = 32.;
+const MAX_NAME_LENGTH: usize = 10;
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// Elo rating, only changed by matches between two profiles.
+    pub rating: f32,
+    pub wins: u32,
+    pub losses: u32,
+    /// Positive for a run of wins, negative for a run of losses.
+    pub streak: i32,
+    pub best_streak: u32,
+    pub best_rally: u32,
+    pub achievements: Vec<Achievement>,
+}
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            rating: STARTING_RATING,
+            wins: 0,
+            losses: 0,
+            streak: 0,
+            best_streak: 0,
+            best_rally: 0,
+            achievements: vec![],
+        }
+    }
+}
+impl Profile {
+    fn record(&mut self, won: bool, longest_rally: u32) {
+        if won {
+            self.wins += 1;
+            self.streak = self.streak.max(0) + 1;
+            self.best_streak = self.best_streak.max(self.streak as u32);
+        } else {
+            self.losses += 1;
+            self.streak = self.streak.min(0) - 1;
+        }
+        self.best_rally = self.best_rally.max(longest_rally);
+    }
+}
+/// Ratings after a player rated `winner` beats one rated `loser`.
+pub fn elo(winner: f32, loser: f32) -> (f32, f32) {
+    let expected = 1. / (1. + 10f32.powf((loser - winner) / 400.));
+    let change = RATING_K * (1. - expected);
+    (winner + change, loser - change)
+}
+/// Every profile on this machine and who is playing each side. Sides without a profile are
+/// guests and don't count towards anything.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+impl Profiles {
+    pub fn save(&self) {
+        save_config(PROFILES_CONFIG, self);
+    }
+    pub fn assigned(&self, player: Player) -> Option<&str> {
+        match player {
+            Player::Left => self.left.as_deref(),
+            Player::Right => self.right.as_deref(),
+        }
+    }
+    fn assigned_mut(&mut self, player: Player) -> &mut Option<String> {
+        match player {
+            Player::Left => &mut self.left,
+            Player::Right => &mut self.right,
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Profile> {
+        self.profiles
+            .iter_mut()
+            .find(|profile| profile.name == name)
+    }
+    /// The profile playing `player`, if any.
+    pub fn player_mut(&mut self, player: Player) -> Option<&mut Profile> {
+        let name = self.assigned(player)?.to_string();
+        self.get_mut(&name)
+    }
+    /// Profiles from the highest rating down.
+    pub fn ranked(&self) -> Vec<&Profile> {
+        let mut ranked: Vec<&Profile> = self.profiles.iter().collect();
+        ranked.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        ranked
+    }
+    /// Adds the result of a finished match to the profiles playing it.
+    fn record_match(&mut self, winner: Player, longest_rally: u32) {
+        let loser = match winner {
+            Player::Left => Player::Right,
+            Player::Right => Player::Left,
+        };
+        for (player, won) in [(winner, true), (loser, false)] {
+            if let Some(profile) = self.player_mut(player) {
+                profile.record(won, longest_rally);
+            }
+        }
+        let ratings = (
+            self.player_mut(winner).map(|profile| profile.rating),
+            self.player_mut(loser).map(|profile| profile.rating),
+        );
+        if let (Some(winner_rating), Some(loser_rating)) = ratings {
+            let (winner_rating, loser_rating) = elo(winner_rating, loser_rating);
+            if let Some(profile) = self.player_mut(winner) {
+                profile.rating = winner_rating;
+            }
+            if let Some(profile) = self.player_mut(loser) {
+                profile.rating = loser_rating;
+            }
+        }
+    }
+}
+/// The name being typed on the new profile screen.
+#[derive(Resource, Default)]
+struct NameEntry(String);
+#[derive(Component)]
+struct NameEntryScreen;
+#[derive(Component)]
+struct NameLine;
+#[derive(Component)]
+struct LeaderboardScreen;
+#[derive(Component)]
+struct RatingText;
+pub struct ProfilesPlugin;
+impl Plugin for ProfilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<Profiles>(PROFILES_CONFIG))
+            .init_resource::<NameEntry>()
+            .add_systems(OnEnter(AppState::Summary), record_match)
+            .add_systems(OnExit(AppState::Summary), despawn_screen::<RatingText>)
+            .add_systems(OnEnter(AppState::NameEntry), spawn_name_entry)
+            .add_systems(
+                Update,
+                name_entry_input.run_if(in_state(AppState::NameEntry)),
+            )
+            .add_systems(
+                OnExit(AppState::NameEntry),
+                despawn_screen::<NameEntryScreen>,
+            )
+            .add_systems(OnEnter(AppState::Leaderboard), spawn_leaderboard)
+            .add_systems(
+                Update,
+                leaderboard_input.run_if(in_state(AppState::Leaderboard)),
+            )
+            .add_systems(
+                OnExit(AppState::Leaderboard),
+                despawn_screen::<LeaderboardScreen>,
+            );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Leaderboard".into(),
+                action: MenuAction::Activate(|world| {
+                    world
+                        .resource_mut::<NextState<AppState>>()
+                        .set(AppState::Leaderboard)
+                }),
+                order: 1,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Left Player".into(),
+                action: MenuAction::Adjust {
+                    value: |world| profile_name(world, Player::Left),
+                    adjust: |world, delta| cycle_profile(world, Player::Left, delta),
+                },
+                order: 8,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Right Player".into(),
+                action: MenuAction::Adjust {
+                    value: |world| profile_name(world, Player::Right),
+                    adjust: |world, delta| cycle_profile(world, Player::Right, delta),
+                },
+                order: 9,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "New Profile".into(),
+                action: MenuAction::Activate(|world| {
+                    world.resource_mut::<NameEntry>().0.clear();
+                    world
+                        .resource_mut::<NextState<AppState>>()
+                        .set(AppState::NameEntry)
+                }),
+                order: 11,
+            },
+        );
+    }
+}
+fn profile_name(world: &World, player: Player) -> String {
+    world
+        .resource::<Profiles>()
+        .assigned(player)
+        .unwrap_or("Guest")
+        .into()
+}
+/// Steps through guest then every profile not already playing the other side.
+fn cycle_profile(world: &mut World, player: Player, delta: i32) {
+    let mut profiles = world.resource_mut::<Profiles>();
+    let other = match player {
+        Player::Left => profiles.right.clone(),
+        Player::Right => profiles.left.clone(),
+    };
+    let choices: Vec<Option<String>> = std::iter::once(None)
+        .chain(
+            profiles
+                .profiles
+                .iter()
+                .filter(|profile| other.as_ref() != Some(&profile.name))
+                .map(|profile| Some(profile.name.clone())),
+        )
+        .collect();
+    let current = profiles.assigned_mut(player);
+    let index = choices
+        .iter()
+        .position(|choice| choice == current)
+        .map_or(0, |index| {
+            (index as i32 + delta).rem_euclid(choices.len() as i32) as usize
+        });
+    *current = choices[index].clone();
+    profiles.save();
+}
+fn record_match(
+    mut commands: Commands,
+    mut profiles: ResMut<Profiles>,
+    stats: Res<MatchStats>,
+    layout: Res<Layout>,
+) {
+    if profiles.left.is_none() && profiles.right.is_none() {
+        return;
+    }
+    let before = [Player::Left, Player::Right].map(|player| {
+        profiles
+            .player_mut(player)
+            .map_or(STARTING_RATING, |profile| profile.rating)
+    });
+    if let Some(winner) = stats.winner() {
+        profiles.record_match(winner, stats.longest_rally);
+        profiles.save();
+    }
+    let sides: Vec<String> = [Player::Left, Player::Right]
+        .into_iter()
+        .map(|player| match profiles.player_mut(player) {
+            Some(profile) => format!(
+                "{} {:.0} ({:+.0})",
+                profile.name,
+                profile.rating,
+                profile.rating - before[player as usize]
+            ),
+            None => "GUEST".into(),
+        })
+        .collect();
+    commands.spawn((
+        BitmapTextBundle::new(
+            BitmapText::new(sides.join("   "), 2.),
+            vec3(0., layout.view_size().y / 2. - 80., 6.),
+        ),
+        RatingText,
+    ));
+}
+fn name_text(name: &str) -> String {
+    format!("NAME: {name}_\n\nENTER: SAVE  ESC: CANCEL\nUP TO {MAX_NAME_LENGTH} LETTERS")
+}
+fn spawn_name_entry(mut commands: Commands, layout: Res<Layout>) {
+    let screen = spawn_screen(
+        &mut commands,
+        layout.view_size(),
+        NameEntryScreen,
+        "NEW PROFILE",
+        8.,
+    );
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn((
+            BitmapTextBundle::new(BitmapText::new(name_text(""), 3.), vec3(0., 0., 1.)),
+            NameLine,
+        ));
+    });
+}
+/// Adds typed characters the bitmap font can show to `name`, up to the longest name allowed.
+pub fn type_name(name: &mut String, characters: &mut EventReader<ReceivedCharacter>) {
+    for character in characters.read().flat_map(|event| event.char.chars()) {
+        //Skip leading spaces, which also drops the space that opened the screen
+        let allowed = glyph_index(character).is_some() || (character == ' ' && !name.is_empty());
+        if allowed && name.chars().count() < MAX_NAME_LENGTH {
+            name.extend(character.to_uppercase());
+        }
+    }
+}
+fn name_entry_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut name: ResMut<NameEntry>,
+    mut profiles: ResMut<Profiles>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut lines: Query<&mut BitmapText, With<NameLine>>,
+) {
+    type_name(&mut name.0, &mut characters);
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        name.0.pop();
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::Menu);
+    } else if keyboard_input.just_pressed(KeyCode::Enter) {
+        let trimmed = name.0.trim().to_string();
+        if trimmed.is_empty() || profiles.get(&trimmed).is_some() {
+            return;
+        }
+        profiles.profiles.push(Profile {
+            name: trimmed,
+            ..Default::default()
+        });
+        profiles.save();
+        next_state.set(AppState::Menu);
+    }
+    if name.is_changed() {
+        for mut text in lines.iter_mut() {
+            text.text = name_text(&name.0);
+        }
+    }
+}
+fn leaderboard_text(profiles: &Profiles) -> String {
+    let mut text = format!(
+        "{:<3}{:<11}{:>7}{:>5}{:>5}{:>7}{:>7}\n\n",
+        "#", "NAME", "RATING", "W", "L", "STREAK", "RALLY"
+    );
+    let ranked = profiles.ranked();
+    if ranked.is_empty() {
+        text.push_str("NO PROFILES YET\n");
+    }
+    for (rank, profile) in ranked.into_iter().take(LEADERBOARD_SIZE).enumerate() {
+        text.push_str(&format!(
+            "{:<3}{:<11}{:>7.0}{:>5}{:>5}{:>7}{:>7}\n",
+            rank + 1,
+            profile.name,
+            profile.rating,
+            profile.wins,
+            profile.losses,
+            profile.best_streak,
+            profile.best_rally,
+        ));
+    }
+    text.push_str("\nESC: BACK");
+    text
+}
+fn spawn_leaderboard(mut commands: Commands, profiles: Res<Profiles>, layout: Res<Layout>) {
+    let screen = spawn_screen(
+        &mut commands,
+        layout.view_size(),
+        LeaderboardScreen,
+        "LEADERBOARD",
+        8.,
+    );
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(BitmapTextBundle::new(
+            BitmapText::new(leaderboard_text(&profiles), 2.5),
+            vec3(0., -20., 1.),
+        ));
+    });
+}
+fn leaderboard_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) || keyboard_input.just_pressed(KeyCode::Enter) {
+        next_state.set(AppState::Menu);
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, rating: f32) -> Profile {
+        Profile {
+            name: name.into(),
+            rating,
+            ..Default::default()
+        }
+    }
+
+    fn profiles(left: Option<&str>, right: Option<&str>) -> Profiles {
+        Profiles {
+            profiles: vec![profile("ADA", 1200.), profile("BOB", 1500.)],
+            left: left.map(Into::into),
+            right: right.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn equal_ratings_trade_half_the_k_factor() {
+        assert_eq!(elo(1200., 1200.), (1216., 1184.));
+    }
+
+    #[test]
+    fn underdog_gains_more_than_the_favourite() {
+        let (favourite, _) = elo(1500., 1200.);
+        let (underdog, loser) = elo(1200., 1500.);
+        let favourite_gain = favourite - 1500.;
+        let underdog_gain = underdog - 1200.;
+        assert!(favourite_gain > 0. && favourite_gain < RATING_K / 2.);
+        assert!(underdog_gain > RATING_K / 2. && underdog_gain < RATING_K);
+        //Rating points are only moved, never made
+        assert_eq!(underdog + loser, 2700.);
+    }
+
+    #[test]
+    fn match_between_profiles_moves_ratings() {
+        let mut profiles = profiles(Some("ADA"), Some("BOB"));
+        profiles.record_match(Player::Left, 12);
+        let (ada, bob) = (profiles.get("ADA").unwrap(), profiles.get("BOB").unwrap());
+        assert!(ada.rating > 1200. && bob.rating < 1500.);
+        assert_eq!((ada.wins, ada.losses, ada.best_rally), (1, 0, 12));
+        assert_eq!((bob.wins, bob.losses, bob.best_rally), (0, 1, 12));
+    }
+
+    #[test]
+    fn guests_leave_ratings_alone() {
+        let mut profiles = profiles(None, Some("BOB"));
+        profiles.record_match(Player::Left, 3);
+        let bob = profiles.get("BOB").unwrap();
+        assert_eq!(bob.rating, 1500.);
+        assert_eq!((bob.wins, bob.losses, bob.streak), (0, 1, -1));
+        assert_eq!(profiles.get("ADA").unwrap().losses, 0);
+    }
+
+    #[test]
+    fn streak_flips_sign_on_a_change_of_fortune() {
+        let mut profile = profile("ADA", 1200.);
+        for (won, streak, best) in [
+            (true, 1, 1),
+            (true, 2, 2),
+            (false, -1, 2),
+            (false, -2, 2),
+            (true, 1, 2),
+            (true, 2, 2),
+            (true, 3, 3),
+        ] {
+            profile.record(won, 0);
+            assert_eq!((profile.streak, profile.best_streak), (streak, best));
+        }
+        assert_eq!((profile.wins, profile.losses), (5, 2));
+    }
+}
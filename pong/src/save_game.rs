@@ -0,0 +1,183 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::layout_plugin::CourtRoot;
+use crate::match_stats::MatchStats;
+use crate::menu_plugin::{add_menu_entry, AppState, MenuAction, MenuEntry};
+use crate::paddle_control::BallState;
+use crate::simulation::PaddleState;
+use crate::{insert_ball, Ball, BallTimer, GameData, Paddle, Player, Score, ServeSeed, Settings};
+
+pub const SAVE_DIR: &str = "./saves";
+const SAVE_FILE: &str = "match.json";
+
+/// Everything needed to carry on a match exactly where it was left.
+#[derive(Serialize, Deserialize)]
+struct MatchSnapshot {
+    settings: Settings,
+    scores: [u32; 2],
+    #[serde(default)]
+    last_ball_speed: Option<f32>,
+    paddles: [PaddleState; 2],
+    ball: Option<BallState>,
+    /// Seconds the serve timer had counted while waiting to serve.
+    serve_elapsed: f32,
+    serve_seed: ServeSeed,
+    stats: MatchStats,
+    /// Paddle hits so far in the point being played.
+    #[serde(default)]
+    rally: u32,
+}
+/// A snapshot loaded by "Continue", applied once the match is back on screen.
+#[derive(Resource, Default)]
+struct PendingRestore(Option<MatchSnapshot>);
+fn save_path() -> PathBuf {
+    PathBuf::from(SAVE_DIR).join(SAVE_FILE)
+}
+/// Keeps a single save slot with the unfinished match, written when the match is paused or the
+/// game is closed mid-match, and cleared when a match ends.
+pub struct SaveGamePlugin;
+impl Plugin for SaveGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingRestore>()
+            .add_systems(OnEnter(AppState::Menu), save_match)
+            .add_systems(OnEnter(AppState::Summary), delete_save)
+            .add_systems(OnEnter(AppState::Playing), restore_match)
+            .add_systems(
+                Last,
+                save_match.run_if(in_state(AppState::Playing).and_then(on_event::<AppExit>())),
+            );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Continue".into(),
+                action: MenuAction::Activate(continue_match),
+                order: -1,
+            },
+        );
+    }
+}
+fn continue_match(world: &mut World) {
+    let path = save_path();
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return;
+    };
+    match serde_json::from_str::<MatchSnapshot>(&contents) {
+        Ok(snapshot) => {
+            world.resource_mut::<PendingRestore>().0 = Some(snapshot);
+            world
+                .resource_mut::<NextState<AppState>>()
+                .set(AppState::Playing);
+        }
+        Err(err) => eprintln!("Error: failed to parse {:?}: {err}", path),
+    }
+}
+fn save_match(
+    settings: Res<Settings>,
+    game_data: Res<GameData>,
+    paddles: Query<(&Transform, &Paddle)>,
+    balls: Query<(&Transform, &Ball)>,
+    ball_timer: Res<BallTimer>,
+    serve_seed: Res<ServeSeed>,
+    stats: Res<MatchStats>,
+) {
+    //Nothing to keep until the match has been played
+    if stats.time_played <= 0. {
+        return;
+    }
+    let mut snapshot = MatchSnapshot {
+        settings: settings.clone(),
+        scores: [game_data.left_score, game_data.right_score],
+        last_ball_speed: game_data.last_ball_speed,
+        paddles: [PaddleState::default(); 2],
+        ball: balls.get_single().ok().map(|(transform, ball)| BallState {
+            position: transform.translation.truncate(),
+            velocity: ball.velocity,
+        }),
+        serve_elapsed: ball_timer.0.elapsed_secs(),
+        serve_seed: *serve_seed,
+        stats: stats.clone(),
+        rally: stats.rally,
+    };
+    for (transform, paddle) in paddles.iter() {
+        snapshot.paddles[paddle.player as usize] = PaddleState {
+            y: transform.translation.y,
+            speed: paddle.speed,
+        };
+    }
+    let written = serde_json::to_string_pretty(&snapshot)
+        .map_err(std::io::Error::other)
+        .and_then(|json| {
+            fs::create_dir_all(SAVE_DIR)?;
+            fs::write(save_path(), json)
+        });
+    if let Err(err) = written {
+        eprintln!("Error: failed to save the match: {err}");
+    }
+}
+fn delete_save() {
+    let path = save_path();
+    if path.exists() {
+        if let Err(err) = fs::remove_file(&path) {
+            eprintln!("Error: failed to remove {:?}: {err}", path);
+        }
+    }
+}
+#[allow(clippy::too_many_arguments)]
+fn restore_match(
+    mut commands: Commands,
+    mut pending: ResMut<PendingRestore>,
+    mut settings: ResMut<Settings>,
+    mut game_data: ResMut<GameData>,
+    mut paddles: Query<(&mut Transform, &mut Paddle), Without<Ball>>,
+    balls: Query<Entity, With<Ball>>,
+    scores: Query<Entity, With<Score>>,
+    mut ball_timer: ResMut<BallTimer>,
+    mut serve_seed: ResMut<ServeSeed>,
+    mut stats: ResMut<MatchStats>,
+    court: Res<CourtRoot>,
+) {
+    let Some(snapshot) = pending.0.take() else {
+        return;
+    };
+    let fullscreen = settings.fullscreen;
+    *settings = snapshot.settings;
+    settings.fullscreen = fullscreen;
+    [game_data.left_score, game_data.right_score] = snapshot.scores;
+    game_data.last_ball_speed = snapshot.last_ball_speed;
+    for (mut transform, mut paddle) in paddles.iter_mut() {
+        let state = snapshot.paddles[paddle.player as usize];
+        transform.translation.y = state.y;
+        paddle.speed = state.speed;
+        match paddle.player {
+            Player::Left => game_data.left_y = state.y,
+            Player::Right => game_data.right_y = state.y,
+        }
+    }
+    for ball in balls.iter() {
+        commands.entity(ball).despawn_recursive();
+    }
+    if let Some(ball) = snapshot.ball {
+        insert_ball(
+            &mut commands,
+            &settings,
+            ball.position,
+            ball.velocity,
+            court.0,
+        );
+    }
+    ball_timer
+        .0
+        .set_elapsed(Duration::from_secs_f32(snapshot.serve_elapsed));
+    *serve_seed = snapshot.serve_seed;
+    *stats = snapshot.stats;
+    stats.rally = snapshot.rally;
+    //update_scores redraws the digits once these are gone
+    for score in scores.iter() {
+        commands.entity(score).despawn_recursive();
+    }
+}
@@ -0,0 +1,310 @@
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::paddle_control::{ControllerContext, ControllerKind, PaddleController, PaddleInput};
+use crate::{Player, PLAY_AREA};
+
+pub const BOT_DIR: &str = "./bots";
+const BOT_EXTENSION: &str = "rhai";
+const TICK_FN: &str = "tick";
+/// Rhai operations a bot may spend deciding a single tick.
+const OPERATION_BUDGET: u64 = 20_000;
+
+/// Names of the bots in the bot folder, without the extension.
+pub fn bot_names() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(BOT_DIR) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == BOT_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+fn bot_path(name: &str) -> PathBuf {
+    PathBuf::from(BOT_DIR).join(format!("{name}.{BOT_EXTENSION}"))
+}
+/// An engine that can only compute: no imports, no `eval`, and limits on how long a tick may
+/// run and how much memory it may build up.
+fn sandbox(name: &str) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .set_max_operations(OPERATION_BUDGET)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(1024)
+        .set_max_array_size(1024)
+        .set_max_map_size(256);
+    //Stdout carries the stats and tournament reports, so script output goes to stderr
+    let print_name = name.to_string();
+    engine.on_print(move |text| eprintln!("[{print_name}] {text}"));
+    let debug_name = name.to_string();
+    engine.on_debug(move |text, _, position| eprintln!("[{debug_name}] {position:?} | {text}"));
+    engine
+}
+/// A compiled bot script. The script defines `fn tick(state)` returning a direction in -1..=1,
+/// and can keep values between ticks on `this`.
+pub struct ScriptBot {
+    engine: Engine,
+    ast: AST,
+    memory: Dynamic,
+}
+impl ScriptBot {
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = bot_path(name);
+        let source =
+            fs::read_to_string(&path).map_err(|err| format!("failed to read {path:?}: {err}"))?;
+        Self::compile(name, &source)
+    }
+    pub fn compile(name: &str, source: &str) -> Result<Self, String> {
+        let engine = sandbox(name);
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == TICK_FN && function.params.len() == 1)
+        {
+            return Err(format!("no `fn {TICK_FN}(state)` defined"));
+        }
+        Ok(Self {
+            engine,
+            ast,
+            memory: Map::new().into(),
+        })
+    }
+    pub fn tick(&mut self, context: &ControllerContext) -> Result<f32, String> {
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.memory);
+        let result = self
+            .engine
+            .call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                TICK_FN,
+                (snapshot(context),),
+            )
+            .map_err(|err| match *err {
+                EvalAltResult::ErrorTooManyOperations(_) => {
+                    format!("over budget of {OPERATION_BUDGET} operations")
+                }
+                err => err.to_string(),
+            })?;
+        let direction = result
+            .as_float()
+            .or_else(|_| result.as_int().map(|direction| direction as FLOAT))
+            .map_err(|type_name| format!("`{TICK_FN}` returned {type_name}, not a number"))?;
+        if !direction.is_finite() {
+            return Err(format!("`{TICK_FN}` returned {direction}"));
+        }
+        Ok((direction as f32).clamp(-1., 1.))
+    }
+}
+fn map<const N: usize>(entries: [(&str, Dynamic); N]) -> Dynamic {
+    Dynamic::from_map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.into(), value))
+            .collect(),
+    )
+}
+fn float(value: f32) -> Dynamic {
+    Dynamic::from_float(value as FLOAT)
+}
+/// The read-only view of the court passed to `tick`. Scripts get a copy, so changing it has no
+/// effect on the game.
+fn snapshot(context: &ControllerContext) -> Dynamic {
+    let settings = context.settings;
+    let paddle = |player: Player| {
+        let x = match player {
+            Player::Left => -settings.paddle_x,
+            Player::Right => settings.paddle_x,
+        };
+        let size = settings.player_paddle_size(player);
+        map([
+            ("x", float(x)),
+            ("y", float(context.paddles[player as usize])),
+            ("width", float(size.x)),
+            ("height", float(size.y)),
+        ])
+    };
+    let opponent = match context.player {
+        Player::Left => Player::Right,
+        Player::Right => Player::Left,
+    };
+    let ball = match context.ball {
+        Some(ball) => map([
+            ("x", float(ball.position.x)),
+            ("y", float(ball.position.y)),
+            ("vx", float(ball.velocity.x)),
+            ("vy", float(ball.velocity.y)),
+            ("size", float(settings.ball_size)),
+        ]),
+        None => Dynamic::UNIT,
+    };
+    map([
+        (
+            "side",
+            match context.player {
+                Player::Left => "left".into(),
+                Player::Right => "right".into(),
+            },
+        ),
+        ("ball", ball),
+        ("paddle", paddle(context.player)),
+        ("opponent", paddle(opponent)),
+        (
+            "area",
+            map([
+                ("width", float(PLAY_AREA.x)),
+                ("height", float(PLAY_AREA.y)),
+            ]),
+        ),
+        ("dt", float(context.delta_seconds)),
+    ])
+}
+/// Drives a paddle with a bot from the bot folder. A bot that fails to load or errors on a tick
+/// leaves its paddle still and keeps the error for the debug overlay.
+pub struct ScriptController {
+    name: String,
+    bot: Result<ScriptBot, String>,
+    error: Option<String>,
+    failures: u32,
+}
+impl ScriptController {
+    pub fn load(name: &str) -> Self {
+        let bot = ScriptBot::load(name);
+        if let Err(err) = &bot {
+            eprintln!("Error: failed to load bot {name}: {err}");
+        }
+        Self {
+            name: name.to_string(),
+            bot,
+            error: None,
+            failures: 0,
+        }
+    }
+    /// Ticks the bot has failed on so far.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+}
+impl PaddleController for ScriptController {
+    fn kind(&self) -> ControllerKind {
+        ControllerKind::Script
+    }
+    fn update(&mut self, context: &ControllerContext) -> PaddleInput {
+        let Ok(bot) = &mut self.bot else {
+            return PaddleInput::default();
+        };
+        match bot.tick(context) {
+            Ok(direction) => PaddleInput::direction(direction),
+            Err(err) => {
+                //Only log new errors, a broken bot fails every tick
+                if self.error.as_ref() != Some(&err) {
+                    eprintln!("Error: bot {} failed: {err}", self.name);
+                }
+                self.error = Some(err);
+                self.failures += 1;
+                PaddleInput::default()
+            }
+        }
+    }
+    fn error(&self) -> Option<&str> {
+        match &self.bot {
+            Ok(_) => self.error.as_deref(),
+            Err(err) => Some(err),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paddle_control::FollowTuning;
+    use crate::simulation::{HeadlessDevices, Simulation};
+    use crate::Settings;
+
+    fn tick(bot: &mut ScriptBot) -> Result<f32, String> {
+        let simulation = Simulation::new(Settings::default(), FollowTuning::default(), 0);
+        let devices = HeadlessDevices::default();
+        bot.tick(&simulation.context(Player::Left, &devices, 1. / 60.))
+    }
+
+    fn controller(source: &str) -> ScriptController {
+        ScriptController {
+            name: "test".into(),
+            bot: ScriptBot::compile("test", source),
+            error: None,
+            failures: 0,
+        }
+    }
+
+    #[test]
+    fn tick_must_be_defined() {
+        assert!(ScriptBot::compile("test", "fn other(state) { 1 }").is_err());
+        assert!(ScriptBot::compile("test", "fn tick() { 1 }").is_err());
+    }
+
+    #[test]
+    fn direction_is_clamped_and_memory_kept() {
+        let source = "fn tick(state) { this.ticks = (this.ticks ?? 0) + 1; this.ticks * 0.75 }";
+        let mut bot = ScriptBot::compile("test", source).unwrap();
+        assert_eq!(tick(&mut bot), Ok(0.75));
+        assert_eq!(tick(&mut bot), Ok(1.));
+        let mut bot = ScriptBot::compile("test", "fn tick(state) { -3 }").unwrap();
+        assert_eq!(tick(&mut bot), Ok(-1.));
+    }
+
+    #[test]
+    fn bad_returns_are_errors() {
+        let mut bot = ScriptBot::compile("test", "fn tick(state) { \"up\" }").unwrap();
+        assert!(tick(&mut bot).is_err());
+        let mut bot = ScriptBot::compile("test", "fn tick(state) { 0.0 / 0.0 }").unwrap();
+        assert!(tick(&mut bot).is_err());
+    }
+
+    #[test]
+    fn runaway_script_is_cut_off() {
+        let mut bot = ScriptBot::compile("test", "fn tick(state) { loop {} }").unwrap();
+        let err = tick(&mut bot).unwrap_err();
+        assert!(err.contains("over budget"), "{err}");
+        //Work well inside the budget still runs
+        let source = "fn tick(state) { let n = 0; for i in 0..1000 { n += i; } n / 1000 }";
+        let mut bot = ScriptBot::compile("test", source).unwrap();
+        assert_eq!(tick(&mut bot), Ok(1.));
+    }
+
+    #[test]
+    fn eval_and_imports_are_disabled() {
+        assert!(ScriptBot::compile("test", "fn tick(state) { eval(\"1\") }").is_err());
+        let source = "fn tick(state) { import \"std\" as std; 0 }";
+        let mut bot = ScriptBot::compile("test", source).unwrap();
+        assert!(tick(&mut bot).is_err());
+    }
+
+    #[test]
+    fn failures_are_counted() {
+        let simulation = Simulation::new(Settings::default(), FollowTuning::default(), 0);
+        let devices = HeadlessDevices::default();
+        let context = simulation.context(Player::Right, &devices, 1. / 60.);
+        let mut runaway = controller("fn tick(state) { loop {} }");
+        for _ in 0..3 {
+            assert_eq!(runaway.update(&context).direction, 0.);
+        }
+        assert_eq!(runaway.failures(), 3);
+        assert!(runaway
+            .error()
+            .is_some_and(|err| err.contains("over budget")));
+        let mut working = controller("fn tick(state) { 1 }");
+        assert_eq!(working.update(&context).direction, 1.);
+        assert_eq!(working.failures(), 0);
+        assert_eq!(working.error(), None);
+    }
+}
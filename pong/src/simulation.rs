@@ -0,0 +1,378 @@
+use bevy::math::vec2;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::layout_plugin::Orientation;
+use crate::paddle_control::{
+    move_paddle, BallState, ControllerContext, FollowTuning, GamepadInput, PaddleController,
+    PaddleInput,
+};
+use crate::{Player, Settings, PLAY_AREA};
+
+/// Seconds between a point being scored and the next serve.
+pub const SERVE_DELAY: f32 = 1.;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PaddleHit {
+    pub player: Player,
+    pub position: Vec2,
+    pub speed: f32,
+    /// Where on the paddle the ball struck, -1 at the bottom to 1 at the top.
+    pub impact_offset: f32,
+}
+/// Where the ball ended up after one step and what it touched on the way.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BallStep {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub hit: Option<PaddleHit>,
+    pub bounced: bool,
+    /// Set when the ball left the court, to the player who won the point.
+    pub scorer: Option<Player>,
+}
+/// The step a ball at `position` moves by, `delta_seconds` scaled by the ball speed handicap of
+/// the half it is in.
+pub fn ball_delta(position: Vec2, settings: &Settings, delta_seconds: f32) -> f32 {
+    let half = if position.x < 0. {
+        Player::Left
+    } else {
+        Player::Right
+    };
+    delta_seconds * settings.handicaps.get(half).ball_speed
+}
+/// Moves the ball one step, bouncing it off the paddles at `paddles` (left and right y) and the
+/// walls. `paddle_scales` stretch the paddles' heights, and `scores` are the left and right
+/// scores, for adaptive speed.
+pub fn step_ball(
+    position: Vec2,
+    mut velocity: Vec2,
+    paddles: [f32; 2],
+    paddle_scales: [f32; 2],
+    scores: [u32; 2],
+    settings: &Settings,
+    delta_seconds: f32,
+) -> BallStep {
+    let delta_seconds = ball_delta(position, settings, delta_seconds);
+    let mut ball_pos = position + velocity * delta_seconds;
+    let mut step = BallStep::default();
+
+    let player = if ball_pos.x < 0. {
+        Player::Left
+    } else {
+        Player::Right
+    };
+    let paddle_x = match player {
+        Player::Left => -settings.paddle_x,
+        Player::Right => settings.paddle_x,
+    };
+    let paddle_y = paddles[player as usize];
+    let paddle_size =
+        settings.player_paddle_size(player) * vec2(1., paddle_scales[player as usize]);
+    if ball_pos.x > paddle_x - paddle_size.x
+        && ball_pos.x < paddle_x + paddle_size.x
+        && ball_pos.y > paddle_y - paddle_size.y
+        && ball_pos.y < paddle_y + paddle_size.y
+    {
+        let lead = scores[player as usize] as i32 - scores[1 - player as usize] as i32;
+        let speed =
+            settings
+                .speed_curve
+                .hit_speed(velocity.length(), settings.speed_multiplier, lead);
+        let pos_difference = position - vec2(paddle_x, paddle_y);
+        let angle = libm::atan2(pos_difference.y as f64, pos_difference.x as f64);
+        velocity.x = speed * angle.cos() as f32;
+        velocity.y = speed * angle.sin() as f32;
+        step.hit = Some(PaddleHit {
+            player,
+            position: ball_pos,
+            speed,
+            impact_offset: (pos_difference.y / paddle_size.y).clamp(-1., 1.),
+        });
+        ball_pos += velocity * delta_seconds;
+    }
+
+    if ball_pos.x - settings.ball_size > PLAY_AREA.x / 2.
+        || ball_pos.x + settings.ball_size < -PLAY_AREA.x / 2.
+    {
+        step.scorer = Some(if ball_pos.x < 0. {
+            Player::Right
+        } else {
+            Player::Left
+        });
+    } else if ball_pos.y + settings.ball_size / 2. > PLAY_AREA.y / 2.
+        || ball_pos.y - settings.ball_size / 2. < -PLAY_AREA.y / 2.
+    {
+        velocity.y = -velocity.y;
+        velocity = velocity.normalize_or_zero()
+            * settings
+                .speed_curve
+                .bounce_speed(velocity.length(), settings.speed_multiplier);
+        ball_pos += velocity * delta_seconds;
+        step.bounced = true;
+    }
+    step.position = ball_pos;
+    step.velocity = velocity;
+    step
+}
+/// Moves a paddle one step according to its input and keeps it on the court, given how much its
+/// height is stretched by `scale`. Returns the moved paddle and the direction it was pushed in.
+pub fn step_paddle(
+    paddle: PaddleState,
+    player: Player,
+    scale: f32,
+    input: PaddleInput,
+    follow: &FollowTuning,
+    settings: &Settings,
+    delta_seconds: f32,
+) -> (PaddleState, f32) {
+    let acceleration = settings.player_paddle_acceleration(player);
+    let direction = match input.target {
+        Some(target) => follow.direction(paddle.y, paddle.speed, target, acceleration),
+        None => input.direction,
+    };
+    let mut paddle = move_paddle(
+        paddle,
+        direction,
+        input.target.is_some(),
+        acceleration,
+        settings.player_max_paddle_speed(player),
+        settings.friction,
+        delta_seconds,
+    );
+    let max_y: f32 = (PLAY_AREA.y - settings.player_paddle_size(player).y * scale) / 2.;
+    if paddle.y.abs() > max_y {
+        paddle.speed = 0.;
+        paddle.y = paddle.y.clamp(-max_y, max_y);
+    }
+    (paddle, direction)
+}
+/// Random starting height and velocity for a new ball, served from the centre line. `carried` is
+/// the speed the last point ended at, if any.
+pub fn serve(rng: &mut impl Rng, settings: &Settings, carried: Option<f32>) -> (f32, Vec2) {
+    let y = rng.gen_range(
+        (-PLAY_AREA.y / 2. + settings.ball_size * 2.)..(PLAY_AREA.y / 2. - settings.ball_size * 2.),
+    );
+    let direction = match rng.gen_range(0..2) {
+        0 => -1,
+        _ => 1,
+    } as f32;
+    let velocity = vec2(
+        rng.gen_range(settings.min_spawn_speed.x..settings.max_spawn_speed.x) * direction,
+        rng.gen_range(settings.min_spawn_speed.y..settings.max_spawn_speed.y) * direction,
+    );
+    (y, settings.speed_curve.serve_velocity(velocity, carried))
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct PaddleState {
+    pub y: f32,
+    pub speed: f32,
+}
+/// What happened over a whole match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchRecord {
+    pub scores: [u32; 2],
+    /// Paddle hits in each point, in order.
+    pub rallies: Vec<u32>,
+    pub max_ball_speed: f32,
+    pub time: f32,
+}
+/// A match with no window or entities, for pitting controllers against each other faster than
+/// real time.
+pub struct Simulation {
+    pub settings: Settings,
+    pub follow: FollowTuning,
+    pub paddles: [PaddleState; 2],
+    pub ball: Option<BallState>,
+    pub scores: [u32; 2],
+    pub time: f32,
+    /// Speed the last point ended at.
+    last_speed: Option<f32>,
+    serve_timer: f32,
+    rng: StdRng,
+}
+impl Simulation {
+    pub fn new(settings: Settings, follow: FollowTuning, seed: u64) -> Self {
+        let scores = [
+            settings.handicaps.left.starting_points,
+            settings.handicaps.right.starting_points,
+        ];
+        Self {
+            settings,
+            follow,
+            paddles: [PaddleState::default(); 2],
+            ball: None,
+            scores,
+            time: 0.,
+            last_speed: None,
+            serve_timer: 0.,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+    pub fn paddle_positions(&self) -> [f32; 2] {
+        [self.paddles[0].y, self.paddles[1].y]
+    }
+    /// Advances the match by one step, returning what the ball did if it was in play.
+    pub fn step(&mut self, inputs: [PaddleInput; 2], delta_seconds: f32) -> Option<BallStep> {
+        self.time += delta_seconds;
+        for ((paddle, input), player) in self
+            .paddles
+            .iter_mut()
+            .zip(inputs)
+            .zip([Player::Left, Player::Right])
+        {
+            (*paddle, _) = step_paddle(
+                *paddle,
+                player,
+                1.,
+                input,
+                &self.follow,
+                &self.settings,
+                delta_seconds,
+            );
+        }
+        let Some(ball) = self.ball else {
+            self.serve_timer += delta_seconds;
+            if self.serve_timer >= SERVE_DELAY {
+                self.serve_timer = 0.;
+                let (y, velocity) = serve(&mut self.rng, &self.settings, self.last_speed);
+                self.ball = Some(BallState {
+                    position: vec2(0., y),
+                    velocity,
+                });
+            }
+            return None;
+        };
+        let step = step_ball(
+            ball.position,
+            ball.velocity,
+            self.paddle_positions(),
+            [1.; 2],
+            self.scores,
+            &self.settings,
+            delta_seconds,
+        );
+        self.ball = match step.scorer {
+            Some(scorer) => {
+                self.scores[scorer as usize] += 1;
+                self.last_speed = Some(step.velocity.length());
+                None
+            }
+            None => Some(BallState {
+                position: step.position,
+                velocity: step.velocity,
+            }),
+        };
+        Some(step)
+    }
+    /// Lets `controllers` play until one side has `points_to_win` or `max_seconds` have passed.
+    pub fn play(
+        mut self,
+        controllers: [&mut dyn PaddleController; 2],
+        points_to_win: u32,
+        max_seconds: f32,
+        delta_seconds: f32,
+    ) -> MatchRecord {
+        let devices = HeadlessDevices::default();
+        let mut record = MatchRecord::default();
+        let mut rally = 0;
+        let [left, right] = controllers;
+        while self.scores.iter().all(|score| *score < points_to_win) && self.time < max_seconds {
+            let inputs = [
+                left.update(&self.context(Player::Left, &devices, delta_seconds)),
+                right.update(&self.context(Player::Right, &devices, delta_seconds)),
+            ];
+            let Some(step) = self.step(inputs, delta_seconds) else {
+                continue;
+            };
+            record.max_ball_speed = record.max_ball_speed.max(step.velocity.length());
+            if step.hit.is_some() {
+                rally += 1;
+            }
+            if step.scorer.is_some() {
+                record.rallies.push(rally);
+                rally = 0;
+            }
+        }
+        record.scores = self.scores;
+        record.time = self.time;
+        record
+    }
+    /// What a controller sees of this match, with no devices attached.
+    pub fn context<'a>(
+        &'a self,
+        player: Player,
+        devices: &'a HeadlessDevices,
+        delta_seconds: f32,
+    ) -> ControllerContext<'a> {
+        ControllerContext {
+            player,
+            ball: self.ball,
+            paddles: self.paddle_positions(),
+            orientation: Orientation::Landscape,
+            keyboard: &devices.keyboard,
+            cursor: None,
+            touches: &[],
+            gamepad: GamepadInput {
+                gamepads: &devices.gamepads,
+                axes: &devices.axes,
+                buttons: &devices.buttons,
+            },
+            settings: &self.settings,
+            delta_seconds,
+        }
+    }
+}
+/// Input devices with nothing pressed, for running controllers headless.
+#[derive(Default)]
+pub struct HeadlessDevices {
+    keyboard: ButtonInput<KeyCode>,
+    gamepads: Gamepads,
+    axes: Axis<GamepadAxis>,
+    buttons: ButtonInput<GamepadButton>,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controllers::{AiController, AiDifficulty};
+
+    fn play(settings: &Settings, seed: u64, max_seconds: f32) -> MatchRecord {
+        let mut left = AiController::new(AiDifficulty::Easy, seed);
+        let mut right = AiController::new(AiDifficulty::Hard, seed + 1);
+        Simulation::new(settings.clone(), FollowTuning::default(), seed).play(
+            [&mut left, &mut right],
+            5,
+            max_seconds,
+            1. / 120.,
+        )
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_match() {
+        let settings = Settings::default();
+        let record = play(&settings, 3, 600.);
+        assert_eq!(play(&settings, 3, 600.), record);
+        assert!(record.scores.contains(&5));
+        assert_eq!(
+            record.rallies.len() as u32,
+            record.scores.iter().sum::<u32>()
+        );
+    }
+
+    #[test]
+    fn match_stops_at_the_time_limit() {
+        let record = play(&Settings::default(), 3, 1.);
+        assert!(record.scores.iter().all(|score| *score < 5));
+        assert!(record.time < 1. + 1. / 60.);
+    }
+
+    #[test]
+    fn head_start_counts_towards_the_win() {
+        let mut settings = Settings::default();
+        settings.handicaps.left.starting_points = 4;
+        let record = play(&settings, 3, 600.);
+        assert!(record.scores[0] >= 4);
+        assert!(record.scores.contains(&5));
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::synth::{Envelope, SynthPreset, Waveform};
+
+const SOUND_PACK_DIR: &str = "./soundpacks";
+pub const BUILTIN_PACKS: [&str; 2] = ["classic", "chiptune"];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SoundPackEntry {
+    Sample { path: String },
+    Synth(SynthPreset),
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SoundPackGroup {
+    pub pitch_variation: f32,
+    pub sounds: Vec<SoundPackEntry>,
+}
+/// Maps sound groups such as `hit`, `bounce` and `death` to samples and synth presets.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SoundPack {
+    pub groups: HashMap<String, SoundPackGroup>,
+}
+impl SoundPack {
+    fn with_group(mut self, name: &str, pitch_variation: f32, sounds: Vec<SoundPackEntry>) -> Self {
+        self.groups.insert(
+            name.to_string(),
+            SoundPackGroup {
+                pitch_variation,
+                sounds,
+            },
+        );
+        self
+    }
+}
+fn sample(path: String) -> SoundPackEntry {
+    SoundPackEntry::Sample { path }
+}
+fn classic() -> SoundPack {
+    SoundPack::default()
+        .with_group(
+            "bounce",
+            0.05,
+            (0..6).map(|i| sample(format!("sounds/{i}.mp3"))).collect(),
+        )
+        .with_group(
+            "hit",
+            0.08,
+            (0..3)
+                .map(|i| sample(format!("sounds/hit{i}.mp3")))
+                .collect(),
+        )
+        .with_group("death", 0., vec![sample("sounds/death.mp3".into())])
+}
+fn chiptune() -> SoundPack {
+    let blip = Envelope {
+        attack: 0.002,
+        decay: 0.04,
+        sustain: 0.5,
+        release: 0.04,
+    };
+    SoundPack::default()
+        .with_group(
+            "bounce",
+            0.03,
+            vec![SoundPackEntry::Synth(SynthPreset {
+                waveform: Waveform::Triangle,
+                frequency: 330.,
+                sweep: 1.,
+                gate: 0.05,
+                envelope: blip,
+                volume: 0.6,
+            })],
+        )
+        .with_group(
+            "hit",
+            0.,
+            vec![SoundPackEntry::Synth(SynthPreset {
+                waveform: Waveform::Square { duty: 0.5 },
+                frequency: 440.,
+                sweep: 1.05,
+                gate: 0.06,
+                envelope: blip,
+                volume: 0.35,
+            })],
+        )
+        .with_group(
+            "death",
+            0.,
+            vec![
+                SoundPackEntry::Synth(SynthPreset {
+                    waveform: Waveform::Square { duty: 0.25 },
+                    frequency: 220.,
+                    sweep: 0.25,
+                    gate: 0.35,
+                    envelope: Envelope {
+                        attack: 0.005,
+                        decay: 0.1,
+                        sustain: 0.6,
+                        release: 0.2,
+                    },
+                    volume: 0.35,
+                }),
+                SoundPackEntry::Synth(SynthPreset {
+                    waveform: Waveform::Noise,
+                    frequency: 4000.,
+                    sweep: 0.1,
+                    gate: 0.3,
+                    envelope: Envelope {
+                        attack: 0.001,
+                        decay: 0.2,
+                        sustain: 0.3,
+                        release: 0.2,
+                    },
+                    volume: 0.3,
+                }),
+            ],
+        )
+}
+/// Built-in packs first, then every `soundpacks/<name>.json` on disk.
+pub fn available_sound_packs() -> Vec<String> {
+    let mut packs: Vec<String> = BUILTIN_PACKS.iter().map(|pack| pack.to_string()).collect();
+    if let Ok(dir) = fs::read_dir(SOUND_PACK_DIR) {
+        let mut custom: Vec<String> = dir
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .filter(|name| !BUILTIN_PACKS.contains(&name.as_str()))
+            .collect();
+        custom.sort();
+        packs.extend(custom);
+    }
+    packs
+}
+pub fn load_sound_pack(name: &str) -> SoundPack {
+    match name {
+        "classic" => return classic(),
+        "chiptune" => return chiptune(),
+        _ => {}
+    }
+    let path = Path::new(SOUND_PACK_DIR).join(format!("{name}.json"));
+    match fs::read_to_string(&path).map(|contents| serde_json::from_str(&contents)) {
+        Ok(Ok(pack)) => pack,
+        Ok(Err(err)) => {
+            eprintln!("Error: failed to parse {:?}: {err}", path);
+            classic()
+        }
+        Err(err) => {
+            eprintln!("Error: failed to read {:?}: {err}", path);
+            classic()
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{load_config, save_config};
+use crate::menu_plugin::{add_menu_entry, on_off, MenuAction, MenuEntry};
+use crate::Settings;
+
+const SPEED_CURVE_CONFIG: &str = "speed_curve";
+const SPEED_CAP_STEP: f32 = 200.;
+const MIN_SPEED_CAP: f32 = 600.;
+const MAX_SPEED_CAP: f32 = 3000.;
+/// How much each point of score difference changes the growth of a return in adaptive mode.
+const ADAPTIVE_STEP: f32 = 0.15;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedGrowth {
+    /// Each hit multiplies the speed by the speed multiplier.
+    Multiply,
+    /// Each hit adds the speed increment.
+    Add,
+}
+/// How the ball speeds up over a rally and across points.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct SpeedCurve {
+    pub growth: SpeedGrowth,
+    /// Speed added by a paddle hit when the growth is additive.
+    pub increment: f32,
+    /// Fastest the ball can go, `None` for no limit.
+    pub max_speed: Option<f32>,
+    /// Serve every point at spawn speed, rather than as fast as the last ball finished.
+    pub reset_each_point: bool,
+    /// Speeds the trailing side's returns up and the leading side's down by the score difference.
+    pub adaptive: bool,
+}
+impl Default for SpeedCurve {
+    fn default() -> Self {
+        Self {
+            growth: SpeedGrowth::Multiply,
+            increment: 40.,
+            max_speed: None,
+            reset_each_point: true,
+            adaptive: false,
+        }
+    }
+}
+impl SpeedCurve {
+    fn cap(&self, speed: f32) -> f32 {
+        self.max_speed.map_or(speed, |max| speed.min(max))
+    }
+    /// Speed of a ball returned at `speed` by a side `lead` points ahead, behind when negative.
+    pub fn hit_speed(&self, speed: f32, multiplier: f32, lead: i32) -> f32 {
+        let mut growth = match self.growth {
+            SpeedGrowth::Multiply => speed * (multiplier - 1.),
+            SpeedGrowth::Add => self.increment,
+        };
+        if self.adaptive {
+            growth *= (1. - lead as f32 * ADAPTIVE_STEP).clamp(0., 2.);
+        }
+        self.cap(speed + growth)
+    }
+    /// Speed of a ball bouncing off a wall at `speed`, growing by a quarter of a hit.
+    pub fn bounce_speed(&self, speed: f32, multiplier: f32) -> f32 {
+        self.cap(match self.growth {
+            SpeedGrowth::Multiply => speed * multiplier.sqrt().sqrt(),
+            SpeedGrowth::Add => speed + self.increment / 4.,
+        })
+    }
+    /// Serve velocity for a new point, sped up to `carried`, the speed the last point ended at,
+    /// unless every point starts afresh.
+    pub fn serve_velocity(&self, velocity: Vec2, carried: Option<f32>) -> Vec2 {
+        match carried {
+            Some(speed) if !self.reset_each_point && speed > velocity.length() => {
+                velocity.normalize_or_zero() * self.cap(speed)
+            }
+            _ => velocity,
+        }
+    }
+}
+/// Lets the menu pick how the ball speeds up, kept between runs.
+pub struct SpeedCurvePlugin;
+impl Plugin for SpeedCurvePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, load_speed_curve);
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Speed Growth".into(),
+                action: MenuAction::Adjust {
+                    value: |world| {
+                        match world.resource::<Settings>().speed_curve.growth {
+                            SpeedGrowth::Multiply => "Multiply",
+                            SpeedGrowth::Add => "Add",
+                        }
+                        .into()
+                    },
+                    adjust: |world, _| {
+                        adjust_curve(world, |curve| {
+                            curve.growth = match curve.growth {
+                                SpeedGrowth::Multiply => SpeedGrowth::Add,
+                                SpeedGrowth::Add => SpeedGrowth::Multiply,
+                            }
+                        })
+                    },
+                },
+                order: 50,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Speed Cap".into(),
+                action: MenuAction::Adjust {
+                    value: |world| match world.resource::<Settings>().speed_curve.max_speed {
+                        Some(speed) => format!("{speed:.0}"),
+                        None => "Off".into(),
+                    },
+                    adjust: |world, delta| {
+                        adjust_curve(world, |curve| {
+                            curve.max_speed = step_cap(curve.max_speed, delta)
+                        })
+                    },
+                },
+                order: 51,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Speed Reset".into(),
+                action: MenuAction::Adjust {
+                    value: |world| {
+                        if world.resource::<Settings>().speed_curve.reset_each_point {
+                            "Each Point"
+                        } else {
+                            "Carry Over"
+                        }
+                        .into()
+                    },
+                    adjust: |world, _| {
+                        adjust_curve(world, |curve| curve.reset_each_point ^= true);
+                    },
+                },
+                order: 52,
+            },
+        );
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Adaptive Speed".into(),
+                action: MenuAction::Adjust {
+                    value: |world| on_off(world.resource::<Settings>().speed_curve.adaptive),
+                    adjust: |world, _| {
+                        adjust_curve(world, |curve| curve.adaptive ^= true);
+                    },
+                },
+                order: 53,
+            },
+        );
+    }
+}
+/// Steps the cap through Off and then from the lowest to the highest cap.
+fn step_cap(max_speed: Option<f32>, delta: i32) -> Option<f32> {
+    let speed = max_speed.unwrap_or(MIN_SPEED_CAP - SPEED_CAP_STEP) + delta as f32 * SPEED_CAP_STEP;
+    if speed < MIN_SPEED_CAP {
+        None
+    } else {
+        Some(speed.min(MAX_SPEED_CAP))
+    }
+}
+fn adjust_curve(world: &mut World, change: impl FnOnce(&mut SpeedCurve)) {
+    let mut settings = world.resource_mut::<Settings>();
+    change(&mut settings.speed_curve);
+    save_config(SPEED_CURVE_CONFIG, &settings.speed_curve);
+}
+fn load_speed_curve(mut settings: ResMut<Settings>) {
+    settings.speed_curve = load_config(SPEED_CURVE_CONFIG);
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec2;
+
+    fn curve(growth: SpeedGrowth) -> SpeedCurve {
+        SpeedCurve {
+            growth,
+            ..Default::default()
+        }
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.01, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn multiply_grows_with_speed_and_add_does_not() {
+        let multiply = curve(SpeedGrowth::Multiply);
+        assert_near(multiply.hit_speed(400., 1.1, 0), 440.);
+        assert_near(multiply.hit_speed(800., 1.1, 0), 880.);
+        assert_near(multiply.bounce_speed(400., 1.1), 400. * 1.1f32.powf(0.25));
+        let add = curve(SpeedGrowth::Add);
+        assert_eq!(add.hit_speed(400., 1.1, 0), 440.);
+        assert_eq!(add.hit_speed(800., 2., 0), 840.);
+        assert_eq!(add.bounce_speed(400., 1.1), 410.);
+    }
+
+    #[test]
+    fn speed_never_passes_the_cap() {
+        for growth in [SpeedGrowth::Multiply, SpeedGrowth::Add] {
+            let capped = SpeedCurve {
+                max_speed: Some(500.),
+                ..curve(growth)
+            };
+            assert_eq!(capped.hit_speed(480., 1.1, 0), 500.);
+            assert_eq!(capped.bounce_speed(499., 1.1), 500.);
+            assert_eq!(capped.hit_speed(500., 1.1, 0), 500.);
+            assert_near(capped.hit_speed(400., 1.1, 0), 440.);
+        }
+    }
+
+    #[test]
+    fn adaptive_growth_follows_the_score() {
+        let adaptive = SpeedCurve {
+            adaptive: true,
+            ..curve(SpeedGrowth::Add)
+        };
+        assert_eq!(adaptive.hit_speed(400., 1.1, 0), 440.);
+        assert_near(adaptive.hit_speed(400., 1.1, 2), 428.);
+        assert_near(adaptive.hit_speed(400., 1.1, -2), 452.);
+        //Large leads clamp rather than slowing the ball or running away
+        assert_eq!(adaptive.hit_speed(400., 1.1, 10), 400.);
+        assert_eq!(adaptive.hit_speed(400., 1.1, -10), 480.);
+    }
+
+    #[test]
+    fn serve_carries_over_only_when_asked() {
+        let serve = vec2(300., 0.);
+        let reset = SpeedCurve::default();
+        assert_eq!(reset.serve_velocity(serve, Some(600.)), serve);
+        let carry = SpeedCurve {
+            reset_each_point: false,
+            ..Default::default()
+        };
+        assert_eq!(carry.serve_velocity(serve, None), serve);
+        assert_eq!(carry.serve_velocity(serve, Some(200.)), serve);
+        let carried = carry.serve_velocity(vec2(-300., 400.), Some(1000.));
+        assert_near(carried.length(), 1000.);
+        assert_near(carried.x / carried.y, -0.75);
+        let capped = SpeedCurve {
+            max_speed: Some(800.),
+            ..carry
+        };
+        assert_near(capped.serve_velocity(serve, Some(1000.)).length(), 800.);
+    }
+
+    #[test]
+    fn cap_steps_from_off_through_the_range() {
+        assert_eq!(step_cap(None, 1), Some(MIN_SPEED_CAP));
+        assert_eq!(step_cap(None, -1), None);
+        assert_eq!(step_cap(Some(MIN_SPEED_CAP), -1), None);
+        assert_eq!(step_cap(Some(800.), 1), Some(1000.));
+        assert_eq!(step_cap(Some(800.), -1), Some(600.));
+        assert_eq!(step_cap(Some(MAX_SPEED_CAP), 1), Some(MAX_SPEED_CAP));
+    }
+}
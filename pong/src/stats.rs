@@ -0,0 +1,449 @@
+use bevy::math::vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::process;
+use std::str::FromStr;
+
+use crate::controllers::{AiController, AiDifficulty};
+use crate::paddle_control::{FollowTuning, PaddleController};
+use crate::script_bot::{ScriptBot, ScriptController};
+use crate::simulation::{MatchRecord, Simulation};
+use crate::speed_curve::SpeedGrowth;
+use crate::{Settings, PLAY_AREA};
+
+const TICK: f32 = 1. / 120.;
+const DEFAULT_MATCHES: u32 = 100;
+const DEFAULT_POINTS: u32 = 5;
+/// Matches neither side has won by then are drawn.
+const MAX_MATCH_SECONDS: f32 = 600.;
+const USAGE: &str = "usage: stats --left CONTROLLER --right CONTROLLER [--matches N] [--seed N] \
+[--points N] [--set SETTING=VALUE]... [--format json|csv]
+controllers: ai, ai:easy, ai:normal, ai:hard, bot:NAME";
+
+/// A controller that can play without a window.
+enum Contender {
+    Ai(AiDifficulty),
+    Bot(String),
+}
+impl Contender {
+    fn parse(spec: &str) -> Result<Self, String> {
+        match spec.split_once(':') {
+            None if spec == "ai" => Ok(Contender::Ai(AiDifficulty::default())),
+            Some(("ai", difficulty)) => AiDifficulty::ALL
+                .into_iter()
+                .find(|known| known.name().eq_ignore_ascii_case(difficulty))
+                .map(Contender::Ai)
+                .ok_or_else(|| format!("unknown AI difficulty {difficulty}")),
+            Some(("bot", name)) => {
+                ScriptBot::load(name).map_err(|err| format!("bot {name}: {err}"))?;
+                Ok(Contender::Bot(name.into()))
+            }
+            _ => Err(format!("unknown controller {spec}")),
+        }
+    }
+    fn label(&self) -> String {
+        match self {
+            Contender::Ai(difficulty) => format!("ai:{}", difficulty.name().to_lowercase()),
+            Contender::Bot(name) => format!("bot:{name}"),
+        }
+    }
+    fn build(&self, seed: u64) -> Box<dyn PaddleController> {
+        match self {
+            Contender::Ai(difficulty) => Box::new(AiController::new(*difficulty, seed)),
+            Contender::Bot(name) => Box::new(ScriptController::load(name)),
+        }
+    }
+}
+/// Applies a `name=value` override, with `x,y` for sizes and speeds and a `left.` or `right.`
+/// prefix for handicaps, as in `left.paddle_scale=1.5`.
+fn set_setting(settings: &mut Settings, assignment: &str) -> Result<(), String> {
+    let (name, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| format!("expected SETTING=VALUE, got {assignment}"))?;
+    let number = |text: &str| {
+        let number = text
+            .trim()
+            .parse::<f32>()
+            .map_err(|err| format!("{name}: {err}"))?;
+        if number.is_nan() || number < 0. {
+            return Err(format!("{name} can't be {number}"));
+        }
+        Ok(number)
+    };
+    let pair = || {
+        let (x, y) = value
+            .split_once(',')
+            .ok_or_else(|| format!("{name} takes x,y"))?;
+        Ok::<_, String>(vec2(number(x)?, number(y)?))
+    };
+    let flag = || {
+        value
+            .trim()
+            .parse::<bool>()
+            .map_err(|err| format!("{name}: {err}"))
+    };
+    match name {
+        "max_paddle_speed" => settings.max_paddle_speed = number(value)?,
+        "paddle_acceleration" => settings.paddle_acceleration = number(value)?,
+        "friction" => settings.friction = number(value)?,
+        "paddle_x" => settings.paddle_x = number(value)?,
+        "ball_size" => settings.ball_size = number(value)?,
+        "speed_multiplier" => settings.speed_multiplier = number(value)?,
+        "paddle_size" => settings.paddle_size = pair()?,
+        "max_spawn_speed" => settings.max_spawn_speed = pair()?,
+        "min_spawn_speed" => settings.min_spawn_speed = pair()?,
+        "speed_growth" => {
+            settings.speed_curve.growth = match value.trim() {
+                "multiply" => SpeedGrowth::Multiply,
+                "add" => SpeedGrowth::Add,
+                _ => return Err(format!("{name} takes multiply or add")),
+            }
+        }
+        "speed_increment" => settings.speed_curve.increment = number(value)?,
+        "max_ball_speed" => settings.speed_curve.max_speed = Some(number(value)?),
+        "reset_each_point" => settings.speed_curve.reset_each_point = flag()?,
+        "adaptive_speed" => settings.speed_curve.adaptive = flag()?,
+        _ => {
+            let handicap = match name.split_once('.') {
+                Some(("left", field)) => (&mut settings.handicaps.left, field),
+                Some(("right", field)) => (&mut settings.handicaps.right, field),
+                _ => return Err(format!("unknown setting {name}")),
+            };
+            match handicap {
+                (handicap, "paddle_scale") => handicap.paddle_scale = number(value)?,
+                (handicap, "speed_scale") => handicap.speed_scale = number(value)?,
+                (handicap, "ball_speed") => handicap.ball_speed = number(value)?,
+                (handicap, "starting_points") => {
+                    handicap.starting_points = value
+                        .trim()
+                        .parse()
+                        .map_err(|err| format!("{name}: {err}"))?
+                }
+                _ => return Err(format!("unknown setting {name}")),
+            }
+        }
+    }
+    check_serve(settings)
+}
+/// Checks the serve can be picked from the spawn speeds and ball size, as `serve` panics on an
+/// empty range.
+fn check_serve(settings: &Settings) -> Result<(), String> {
+    let (min, max) = (settings.min_spawn_speed, settings.max_spawn_speed);
+    if min.x >= max.x || min.y >= max.y {
+        return Err(format!(
+            "min_spawn_speed {},{} must be below max_spawn_speed {},{} on both axes",
+            min.x, min.y, max.x, max.y
+        ));
+    }
+    if settings.ball_size >= PLAY_AREA.y / 4. {
+        return Err(format!(
+            "ball_size must be below {}, a quarter of the court height",
+            PLAY_AREA.y / 4.
+        ));
+    }
+    Ok(())
+}
+/// The matches to play, from the command line.
+struct Options {
+    left: Contender,
+    right: Contender,
+    matches: u32,
+    seed: u64,
+    points_to_win: u32,
+    settings: Settings,
+    /// The `--set` overrides as given, for the report.
+    overrides: Vec<String>,
+}
+#[derive(Serialize, Debug, PartialEq)]
+struct Report {
+    left: String,
+    right: String,
+    settings: Vec<String>,
+    matches: u32,
+    seed: u64,
+    points_to_win: u32,
+    left_wins: u32,
+    right_wins: u32,
+    draws: u32,
+    left_win_rate: f32,
+    right_win_rate: f32,
+    /// Paddle hits per point.
+    average_rally: f32,
+    longest_rally: u32,
+    max_ball_speed: f32,
+    /// How many matches ended with each score, written "left-right".
+    scores: BTreeMap<String, u32>,
+}
+impl Report {
+    fn csv(&self) -> String {
+        let join = |values: Vec<String>| values.join(";");
+        let header = "left,right,settings,matches,seed,points_to_win,left_wins,right_wins,draws,\
+left_win_rate,right_win_rate,average_rally,longest_rally,max_ball_speed,scores";
+        let row = [
+            self.left.clone(),
+            self.right.clone(),
+            join(self.settings.clone()),
+            self.matches.to_string(),
+            self.seed.to_string(),
+            self.points_to_win.to_string(),
+            self.left_wins.to_string(),
+            self.right_wins.to_string(),
+            self.draws.to_string(),
+            self.left_win_rate.to_string(),
+            self.right_win_rate.to_string(),
+            self.average_rally.to_string(),
+            self.longest_rally.to_string(),
+            self.max_ball_speed.to_string(),
+            join(
+                self.scores
+                    .iter()
+                    .map(|(score, count)| format!("{score}:{count}"))
+                    .collect(),
+            ),
+        ];
+        let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        format!("{header}\n{}", row.join(","))
+    }
+}
+/// Quotes a field when it holds a comma or quote, as with `paddle_size=10,60`.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+fn fail(message: &str) -> ! {
+    eprintln!("Error: {message}\n{USAGE}");
+    process::exit(2);
+}
+fn number<T: FromStr>(arg: &str, text: String) -> T {
+    text.parse()
+        .unwrap_or_else(|_| fail(&format!("{arg} needs a number, got {text}")))
+}
+/// Plays `--matches` seeded matches between two controllers with no window and prints
+/// statistics, for checking how changes to [`Settings`] affect balance.
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut left = None;
+    let mut right = None;
+    let mut matches = DEFAULT_MATCHES;
+    let mut seed = 0;
+    let mut points_to_win = DEFAULT_POINTS;
+    let mut settings = Settings::default();
+    let mut overrides = vec![];
+    let mut csv = false;
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .unwrap_or_else(|| fail(&format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "--left" => left = Some(Contender::parse(&value()).unwrap_or_else(|err| fail(&err))),
+            "--right" => right = Some(Contender::parse(&value()).unwrap_or_else(|err| fail(&err))),
+            "--matches" => matches = number(&arg, value()),
+            "--seed" => seed = number(&arg, value()),
+            "--points" => points_to_win = number(&arg, value()),
+            "--set" => {
+                let assignment = value();
+                set_setting(&mut settings, &assignment).unwrap_or_else(|err| fail(&err));
+                overrides.push(assignment);
+            }
+            "--format" => match value().as_str() {
+                "json" => csv = false,
+                "csv" => csv = true,
+                format => fail(&format!("unknown format {format}")),
+            },
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => fail(&format!("unknown argument {arg}")),
+        }
+    }
+    let (Some(left), Some(right)) = (left, right) else {
+        fail("both --left and --right are needed");
+    };
+    if matches == 0 || points_to_win == 0 {
+        fail("--matches and --points must be at least 1");
+    }
+    let report = play(&Options {
+        left,
+        right,
+        matches,
+        seed,
+        points_to_win,
+        settings,
+        overrides,
+    });
+    if csv {
+        println!("{}", report.csv());
+    } else {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{json}"),
+            Err(err) => fail(&err.to_string()),
+        }
+    }
+}
+fn play(options: &Options) -> Report {
+    let Options {
+        left,
+        right,
+        matches,
+        seed,
+        points_to_win,
+        settings,
+        overrides,
+    } = options;
+    let (matches, seed, points_to_win) = (*matches, *seed, *points_to_win);
+    //Derive every seed from --seed so runs can be repeated exactly
+    let mut seeds = StdRng::seed_from_u64(seed);
+    let records: Vec<MatchRecord> = (0..matches)
+        .map(|_| {
+            let simulation =
+                Simulation::new(settings.clone(), FollowTuning::default(), seeds.gen());
+            let mut left = left.build(seeds.gen());
+            let mut right = right.build(seeds.gen());
+            simulation.play(
+                [left.as_mut(), right.as_mut()],
+                points_to_win,
+                MAX_MATCH_SECONDS,
+                TICK,
+            )
+        })
+        .collect();
+
+    //Only a side that reached the points to win has won, a match cut off by the time limit is
+    //drawn whatever the score
+    let wins = |side: usize| {
+        records
+            .iter()
+            .filter(|record| record.scores[side] >= points_to_win)
+            .count() as u32
+    };
+    let (left_wins, right_wins) = (wins(0), wins(1));
+    let rallies: Vec<u32> = records
+        .iter()
+        .flat_map(|record| record.rallies.iter().copied())
+        .collect();
+    let mut scores = BTreeMap::new();
+    for record in &records {
+        *scores
+            .entry(format!("{}-{}", record.scores[0], record.scores[1]))
+            .or_insert(0) += 1;
+    }
+    Report {
+        left: left.label(),
+        right: right.label(),
+        settings: overrides.clone(),
+        matches,
+        seed,
+        points_to_win,
+        left_wins,
+        right_wins,
+        draws: matches - left_wins - right_wins,
+        left_win_rate: left_wins as f32 / matches as f32,
+        right_win_rate: right_wins as f32 / matches as f32,
+        average_rally: rallies.iter().sum::<u32>() as f32 / rallies.len().max(1) as f32,
+        longest_rally: rallies.iter().copied().max().unwrap_or(0),
+        max_ball_speed: records
+            .iter()
+            .map(|record| record.max_ball_speed)
+            .fold(0., f32::max),
+        scores,
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(seed: u64) -> Options {
+        Options {
+            left: Contender::Ai(AiDifficulty::Easy),
+            right: Contender::Ai(AiDifficulty::Hard),
+            matches: 4,
+            seed,
+            points_to_win: 3,
+            settings: Settings::default(),
+            overrides: vec![],
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_report() {
+        let report = play(&options(7));
+        assert_eq!(play(&options(7)), report);
+        assert_eq!(report.left_wins + report.right_wins + report.draws, 4);
+    }
+
+    #[test]
+    fn set_setting_parses_values() {
+        let mut settings = Settings::default();
+        set_setting(&mut settings, "ball_size=12").unwrap();
+        assert_eq!(settings.ball_size, 12.);
+        set_setting(&mut settings, "paddle_size= 10 , 60").unwrap();
+        assert_eq!(settings.paddle_size, vec2(10., 60.));
+        set_setting(&mut settings, "speed_growth=add").unwrap();
+        assert_eq!(settings.speed_curve.growth, SpeedGrowth::Add);
+        set_setting(&mut settings, "max_ball_speed=900").unwrap();
+        assert_eq!(settings.speed_curve.max_speed, Some(900.));
+        set_setting(&mut settings, "reset_each_point=false").unwrap();
+        assert!(!settings.speed_curve.reset_each_point);
+        set_setting(&mut settings, "left.paddle_scale=1.5").unwrap();
+        assert_eq!(settings.handicaps.left.paddle_scale, 1.5);
+        set_setting(&mut settings, "right.starting_points=2").unwrap();
+        assert_eq!(settings.handicaps.right.starting_points, 2);
+        //Values that would leave serve an empty range to pick from
+        for assignment in [
+            "min_spawn_speed=300,0",
+            "max_spawn_speed=150,0",
+            "ball_size=120",
+            "left.paddle_scale=-1",
+            "right.ball_speed=NaN",
+            "speed_multiplier=-0.5",
+        ] {
+            let before = settings.clone();
+            assert!(
+                set_setting(&mut settings, assignment).is_err(),
+                "{assignment}"
+            );
+            settings = before;
+        }
+        set_setting(&mut settings, "ball_size=100").unwrap();
+        assert_eq!(settings.ball_size, 100.);
+    }
+
+    #[test]
+    fn set_setting_rejects_bad_input() {
+        let mut settings = Settings::default();
+        for assignment in [
+            "ball_size",
+            "ball_size=big",
+            "paddle_size=10",
+            "speed_growth=square",
+            "adaptive_speed=yes",
+            "unknown=1",
+            "middle.paddle_scale=1",
+            "left.colour=1",
+            "left.starting_points=-1",
+        ] {
+            assert!(
+                set_setting(&mut settings, assignment).is_err(),
+                "{assignment}"
+            );
+        }
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("ai:hard"), "ai:hard");
+        assert_eq!(csv_field("paddle_size=10,60"), "\"paddle_size=10,60\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        let mut report = play(&options(1));
+        report.settings = vec!["paddle_size=10,60".into(), "ball_size=12".into()];
+        let csv = report.csv();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.starts_with("ai:easy,ai:hard,\"paddle_size=10,60;ball_size=12\",4,1,3,"));
+    }
+}
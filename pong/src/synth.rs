@@ -0,0 +1,142 @@
+use bevy::audio::{Decodable, Source};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Square { duty: f32 },
+    Triangle,
+    Noise,
+}
+/// Times are in seconds, `sustain` is a level between 0 and 1.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Envelope {
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+impl Envelope {
+    fn level(&self, time: f32, gate: f32) -> f32 {
+        if time >= gate {
+            let released = time - gate;
+            let start = self.held_level(gate);
+            if self.release <= 0. {
+                return 0.;
+            }
+            return (start * (1. - released / self.release)).max(0.);
+        }
+        self.held_level(time)
+    }
+    fn held_level(&self, time: f32) -> f32 {
+        if time < self.attack {
+            return time / self.attack;
+        }
+        let time = time - self.attack;
+        if time < self.decay {
+            return 1. - (1. - self.sustain) * time / self.decay;
+        }
+        self.sustain
+    }
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct SynthPreset {
+    pub waveform: Waveform,
+    pub frequency: f32,
+    /// Frequency multiplier reached by the end of the note, for chirps and drops.
+    pub sweep: f32,
+    /// How long the note is held before the release starts.
+    pub gate: f32,
+    pub envelope: Envelope,
+    pub volume: f32,
+}
+impl SynthPreset {
+    pub fn duration(&self) -> f32 {
+        self.gate + self.envelope.release
+    }
+}
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct SynthSound(pub SynthPreset);
+impl Decodable for SynthSound {
+    type DecoderItem = f32;
+    type Decoder = SynthDecoder;
+
+    fn decoder(&self) -> Self::Decoder {
+        SynthDecoder {
+            preset: self.0,
+            sample: 0,
+            total_samples: (self.0.duration() * SAMPLE_RATE as f32) as u64,
+            phase: 0.,
+            noise: 0x7fff,
+        }
+    }
+}
+pub struct SynthDecoder {
+    preset: SynthPreset,
+    sample: u64,
+    total_samples: u64,
+    phase: f32,
+    noise: u16,
+}
+impl SynthDecoder {
+    fn oscillator(&mut self) -> f32 {
+        match self.preset.waveform {
+            Waveform::Square { duty } => {
+                if self.phase < duty {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            Waveform::Triangle => 4. * (self.phase - 0.5).abs() - 1.,
+            Waveform::Noise => {
+                if self.noise & 1 == 1 {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+        }
+    }
+}
+impl Iterator for SynthDecoder {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.sample >= self.total_samples {
+            return None;
+        }
+        let time = self.sample as f32 / SAMPLE_RATE as f32;
+        let progress = self.sample as f32 / self.total_samples.max(1) as f32;
+        let frequency = self.preset.frequency * self.preset.sweep.powf(progress);
+        let value = self.oscillator()
+            * self.preset.envelope.level(time, self.preset.gate)
+            * self.preset.volume;
+        let previous_phase = self.phase;
+        self.phase = (self.phase + frequency / SAMPLE_RATE as f32).fract();
+        if self.phase < previous_phase {
+            //Clock the NES style noise register once per period
+            let bit = (self.noise ^ (self.noise >> 1)) & 1;
+            self.noise = (self.noise >> 1) | (bit << 14);
+        }
+        self.sample += 1;
+        Some(value)
+    }
+}
+impl Source for SynthDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some((self.total_samples - self.sample.min(self.total_samples)) as usize)
+    }
+    fn channels(&self) -> u16 {
+        1
+    }
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(self.preset.duration()))
+    }
+}
@@ -0,0 +1,285 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext, LoadedFolder};
+use bevy::prelude::*;
+use bevy::utils::BoxedFuture;
+use bevy_prototype_lyon::prelude::*;
+use common_modules::bitmap_font_plugin::BitmapText;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{load_config, save_config};
+use crate::menu_plugin::{add_menu_entry, MenuAction, MenuEntry};
+use crate::{asset_folder_exists, Player};
+
+const THEME_CONFIG: &str = "theme";
+
+mod hex_color {
+    use bevy::prelude::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.as_rgba_u8();
+        serializer.serialize_str(&format!("#{r:02x}{g:02x}{b:02x}{a:02x}"))
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Color::hex(&hex).map_err(|err| D::Error::custom(format!("{hex}: {err}")))
+    }
+}
+/// Colours for every part of the court. Loaded from `themes/*.theme` files as well as the
+/// built-in palettes.
+#[derive(Asset, TypePath, Serialize, Deserialize, Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    #[serde(with = "hex_color")]
+    pub court_lines: Color,
+    #[serde(with = "hex_color")]
+    pub left_paddle: Color,
+    #[serde(with = "hex_color")]
+    pub right_paddle: Color,
+    #[serde(with = "hex_color")]
+    pub ball: Color,
+    #[serde(with = "hex_color")]
+    pub score: Color,
+    #[serde(with = "hex_color")]
+    pub accent: Color,
+}
+impl Theme {
+    pub fn color(&self, role: ThemeRole) -> Color {
+        match role {
+            ThemeRole::Background => self.background,
+            ThemeRole::CourtLines => self.court_lines,
+            ThemeRole::Paddle(Player::Left) => self.left_paddle,
+            ThemeRole::Paddle(Player::Right) => self.right_paddle,
+            ThemeRole::Ball => self.ball,
+            ThemeRole::Score => self.score,
+            ThemeRole::Accent => self.accent,
+        }
+    }
+}
+fn builtin_themes() -> Vec<Theme> {
+    let classic = |name: &str, background: Color| Theme {
+        name: name.into(),
+        background,
+        court_lines: Color::WHITE,
+        left_paddle: Color::WHITE,
+        right_paddle: Color::WHITE,
+        ball: Color::WHITE,
+        score: Color::WHITE,
+        accent: Color::rgb(1., 0.85, 0.2),
+    };
+    vec![
+        classic("Teal", Color::rgb(0., 0.4, 0.4)),
+        classic("Violet", Color::rgb(0.2, 0., 0.8)),
+        classic("Magenta", Color::rgb(1., 0.2, 0.6)),
+        classic("Classic", Color::BLACK),
+        Theme {
+            name: "High Contrast".into(),
+            background: Color::BLACK,
+            court_lines: Color::rgb(0.6, 0.6, 0.6),
+            left_paddle: Color::WHITE,
+            right_paddle: Color::WHITE,
+            ball: Color::rgb(1., 1., 0.),
+            score: Color::WHITE,
+            accent: Color::rgb(1., 1., 0.),
+        },
+        //Okabe-Ito palette, distinguishable with the common colour vision deficiencies
+        Theme {
+            name: "Colour Blind".into(),
+            background: Color::rgb_u8(0x1a, 0x1a, 0x1a),
+            court_lines: Color::rgb_u8(0x99, 0x99, 0x99),
+            left_paddle: Color::rgb_u8(0x00, 0x72, 0xb2),
+            right_paddle: Color::rgb_u8(0xe6, 0x9f, 0x00),
+            ball: Color::WHITE,
+            score: Color::rgb_u8(0xf0, 0xe4, 0x42),
+            accent: Color::rgb_u8(0x56, 0xb4, 0xe9),
+        },
+    ]
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThemeRole {
+    Background,
+    CourtLines,
+    Paddle(Player),
+    Ball,
+    Score,
+    Accent,
+}
+/// Colours the entity's `Fill`, `Sprite` or `BitmapText` from the active theme.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Themed(pub ThemeRole);
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub theme: String,
+}
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            theme: "Teal".into(),
+        }
+    }
+}
+#[derive(Resource)]
+pub struct ActiveTheme(pub Theme);
+#[derive(Resource, Default)]
+struct Themes {
+    builtin: Vec<Handle<Theme>>,
+    folder: Option<Handle<LoadedFolder>>,
+}
+impl Themes {
+    fn all<'a>(
+        &'a self,
+        themes: &'a Assets<Theme>,
+        folders: &Assets<LoadedFolder>,
+    ) -> Vec<&'a Theme> {
+        let mut all: Vec<&Theme> = self
+            .builtin
+            .iter()
+            .filter_map(|handle| themes.get(handle))
+            .collect();
+        if let Some(folder) = self.folder.as_ref().and_then(|folder| folders.get(folder)) {
+            all.extend(
+                folder
+                    .handles
+                    .iter()
+                    .filter_map(|handle| handle.clone().try_typed::<Theme>().ok())
+                    .filter_map(|handle| themes.get(&handle)),
+            );
+        }
+        all
+    }
+}
+#[derive(Default)]
+struct ThemeLoader;
+impl AssetLoader for ThemeLoader {
+    type Asset = Theme;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Theme, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            serde_json::from_slice(&bytes)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+        })
+    }
+    fn extensions(&self) -> &[&str] {
+        &["theme"]
+    }
+}
+pub struct ThemePlugin;
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let settings = load_config::<ThemeSettings>(THEME_CONFIG);
+        let active = builtin_themes()
+            .into_iter()
+            .find(|theme| theme.name == settings.theme)
+            .unwrap_or_else(|| builtin_themes().remove(0));
+        app.init_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .insert_resource(settings)
+            .insert_resource(ActiveTheme(active))
+            .init_resource::<Themes>()
+            .add_systems(Startup, load_themes)
+            .add_systems(Update, select_theme)
+            .add_systems(PostUpdate, apply_theme);
+        add_menu_entry(
+            app,
+            MenuEntry {
+                label: "Theme".into(),
+                action: MenuAction::Adjust {
+                    value: |world| world.resource::<ThemeSettings>().theme.to_uppercase(),
+                    adjust: cycle_theme,
+                },
+                order: 10,
+            },
+        );
+    }
+}
+fn load_themes(
+    mut themes: ResMut<Themes>,
+    mut theme_assets: ResMut<Assets<Theme>>,
+    asset_server: Res<AssetServer>,
+) {
+    themes.builtin = builtin_themes()
+        .into_iter()
+        .map(|theme| theme_assets.add(theme))
+        .collect();
+    if asset_folder_exists(&asset_server, "themes") {
+        themes.folder = Some(asset_server.load_folder("themes"));
+    }
+}
+fn cycle_theme(world: &mut World, delta: i32) {
+    let names: Vec<String> = {
+        let themes = world.resource::<Themes>();
+        themes
+            .all(
+                world.resource::<Assets<Theme>>(),
+                world.resource::<Assets<LoadedFolder>>(),
+            )
+            .iter()
+            .map(|theme| theme.name.clone())
+            .collect()
+    };
+    if names.is_empty() {
+        return;
+    }
+    let mut settings = world.resource_mut::<ThemeSettings>();
+    let current = names
+        .iter()
+        .position(|name| *name == settings.theme)
+        .unwrap_or(0) as i32;
+    settings.theme = names[(current + delta).rem_euclid(names.len() as i32) as usize].clone();
+    save_config(THEME_CONFIG, settings.as_ref());
+}
+fn select_theme(
+    settings: Res<ThemeSettings>,
+    themes: Res<Themes>,
+    theme_assets: Res<Assets<Theme>>,
+    folders: Res<Assets<LoadedFolder>>,
+    mut active: ResMut<ActiveTheme>,
+    mut theme_events: EventReader<AssetEvent<Theme>>,
+) {
+    let reloaded = theme_events.read().count() > 0;
+    if !settings.is_changed() && !reloaded {
+        return;
+    }
+    if let Some(theme) = themes
+        .all(&theme_assets, &folders)
+        .into_iter()
+        .find(|theme| theme.name == settings.theme)
+    {
+        active.0 = theme.clone();
+    }
+}
+fn apply_theme(
+    active: Res<ActiveTheme>,
+    mut shapes: Query<(Ref<Themed>, &mut Fill)>,
+    mut sprites: Query<(Ref<Themed>, &mut Sprite), Without<Fill>>,
+    mut texts: Query<(Ref<Themed>, &mut BitmapText)>,
+) {
+    let changed = active.is_changed();
+    for (themed, mut fill) in shapes.iter_mut() {
+        if changed || themed.is_added() {
+            fill.color = active.0.color(themed.0);
+        }
+    }
+    for (themed, mut sprite) in sprites.iter_mut() {
+        if changed || themed.is_added() {
+            sprite.color = active.0.color(themed.0);
+        }
+    }
+    for (themed, mut text) in texts.iter_mut() {
+        if changed || themed.is_added() {
+            text.color = active.0.color(themed.0);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::paddle_control::FollowTuning;
+use crate::script_bot::{bot_names, ScriptBot, ScriptController, BOT_DIR};
+use crate::simulation::Simulation;
+use crate::Settings;
+
+const TICK: f32 = 1. / 120.;
+const POINTS_TO_WIN: u32 = 5;
+/// Matches neither bot has won by then are drawn, in case neither can score.
+const MAX_MATCH_SECONDS: f32 = 600.;
+const DEFAULT_ROUNDS: u32 = 2;
+
+#[derive(Default)]
+struct Standing {
+    name: String,
+    wins: u32,
+    losses: u32,
+    draws: u32,
+    points_for: u32,
+    points_against: u32,
+    failed_ticks: u32,
+}
+struct MatchResult {
+    scores: [u32; 2],
+    failed_ticks: [u32; 2],
+}
+/// Plays every bot against every other bot with no window and prints the standings.
+/// Takes `[--rounds N] [bot...]`, using every bot in the bot folder when none are named.
+pub fn run(mut args: impl Iterator<Item = String>) {
+    let mut rounds = DEFAULT_ROUNDS;
+    let mut names = vec![];
+    while let Some(arg) = args.next() {
+        if arg == "--rounds" {
+            match args.next().and_then(|rounds| rounds.parse().ok()) {
+                Some(value) => rounds = value,
+                None => {
+                    eprintln!("Error: --rounds needs a number");
+                    return;
+                }
+            }
+        } else {
+            names.push(arg);
+        }
+    }
+    if names.is_empty() {
+        names = bot_names();
+    }
+    //Leave out bots that don't compile rather than have them forfeit every match
+    names.retain(|name| match ScriptBot::load(name) {
+        Ok(_) => true,
+        Err(err) => {
+            eprintln!("Error: leaving out bot {name}: {err}");
+            false
+        }
+    });
+    if names.len() < 2 {
+        eprintln!("Error: a tournament needs at least two working bots in {BOT_DIR}");
+        return;
+    }
+    let mut standings: Vec<Standing> = names
+        .iter()
+        .map(|name| Standing {
+            name: name.clone(),
+            ..Default::default()
+        })
+        .collect();
+    let mut seed = 0;
+    for first in 0..names.len() {
+        for second in first + 1..names.len() {
+            for round in 0..rounds {
+                //Alternate sides between rounds
+                let sides = if round % 2 == 0 {
+                    [first, second]
+                } else {
+                    [second, first]
+                };
+                let result = play_match(&names[sides[0]], &names[sides[1]], seed);
+                seed += 1;
+                println!(
+                    "{} {} - {} {}",
+                    names[sides[0]], result.scores[0], result.scores[1], names[sides[1]]
+                );
+                for (side, index) in sides.into_iter().enumerate() {
+                    let standing = &mut standings[index];
+                    let (own, other) = (result.scores[side], result.scores[1 - side]);
+                    standing.points_for += own;
+                    standing.points_against += other;
+                    standing.failed_ticks += result.failed_ticks[side];
+                    match own.cmp(&other) {
+                        std::cmp::Ordering::Greater => standing.wins += 1,
+                        std::cmp::Ordering::Less => standing.losses += 1,
+                        std::cmp::Ordering::Equal => standing.draws += 1,
+                    }
+                }
+            }
+        }
+    }
+    standings.sort_by_key(|standing| {
+        std::cmp::Reverse((
+            standing.wins * 2 + standing.draws,
+            standing.points_for as i64 - standing.points_against as i64,
+        ))
+    });
+    println!();
+    println!(
+        "{:<20} {:>4} {:>4} {:>4} {:>6} {:>6} {:>8}",
+        "Bot", "W", "L", "D", "For", "Ag", "Errors"
+    );
+    for standing in standings {
+        println!(
+            "{:<20} {:>4} {:>4} {:>4} {:>6} {:>6} {:>8}",
+            standing.name,
+            standing.wins,
+            standing.losses,
+            standing.draws,
+            standing.points_for,
+            standing.points_against,
+            standing.failed_ticks
+        );
+    }
+}
+fn play_match(left: &str, right: &str, seed: u64) -> MatchResult {
+    let mut left = ScriptController::load(left);
+    let mut right = ScriptController::load(right);
+    let record = Simulation::new(Settings::default(), FollowTuning::default(), seed).play(
+        [&mut left, &mut right],
+        POINTS_TO_WIN,
+        MAX_MATCH_SECONDS,
+        TICK,
+    );
+    MatchResult {
+        scores: record.scores,
+        failed_ticks: [left.failures(), right.failures()],
+    }
+}